@@ -11,6 +11,66 @@ int _seq = 0;
 // 响应流
 Map<int, Completer<Uint8List>> _reqMap = {};
 Map<int, StreamController<Uint8List>> _reqStreamMap = {};
+// 进度回调
+Map<int, void Function(ProgressMsg)> _progressMap = {};
+// 请求的取消标识
+Map<int, CancelToken> _cancelMap = {};
+
+// 服务错误码 与后端ServiceError保持一致
+class ErrorCode {
+  static const int notLoggedIn = 1001;
+  static const int network = 2001;
+  static const int config = 3001;
+  static const int invalidInput = 4001;
+  static const int notFound = 4004;
+  static const int remote = 5001;
+  static const int locked = 5002;
+  static const int cancelled = 6001;
+  static const int timeout = 6002;
+  static const int internal = 9001;
+}
+
+// 服务返回的错误
+// toString为面向用户的错误信息
+class ApiException implements Exception {
+  final int code;
+  final ErrorCategoryMsg category;
+  final String message;
+  final String? detail;
+
+  ApiException(this.code, this.category, this.message, {this.detail});
+
+  // 由响应生成 旧版本响应无结构化错误时视为内部错误
+  factory ApiException.fromResponse(BaseResponse rsp) {
+    final e = rsp.error;
+    if (e == null) {
+      return ApiException(
+          ErrorCode.internal, ErrorCategoryMsg.internal, rsp.msg);
+    }
+    return ApiException(e.code, e.category, e.message, detail: e.detail);
+  }
+
+  bool get isCancelled => code == ErrorCode.cancelled;
+
+  @override
+  String toString() => message;
+}
+
+// 请求取消标识
+// 传入请求后，调用cancel取消所有使用该标识的进行中请求
+class CancelToken {
+  final Set<int> _ids = {};
+  bool _isCancelled = false;
+
+  bool get isCancelled => _isCancelled;
+
+  void cancel() {
+    _isCancelled = true;
+    for (final id in _ids.toList()) {
+      cancelRequest(id);
+    }
+  }
+}
 
 // 启动监听响应数据
 void initMsg() {
@@ -20,6 +80,13 @@ void initMsg() {
       _handleStream(data);
       return;
     }
+    if (!rsp.isEnd) {
+      // 进度帧
+      _progressMap[rsp.id]?.call(ProgressMsg.bincodeDeserialize(data.binary));
+      return;
+    }
+    _progressMap.remove(rsp.id);
+    _releaseCancel(rsp.id);
     var complete = _reqMap.remove(rsp.id);
     if (complete == null) {
       // 错误，没有请求id
@@ -29,7 +96,7 @@ void initMsg() {
     if (rsp.msg.isNotEmpty) {
       // 错误：处理错误
       // error(rsp.msg);
-      complete.completeError(rsp.msg);
+      complete.completeError(ApiException.fromResponse(rsp));
     } else {
       complete.complete(data.binary);
     }
@@ -44,20 +111,32 @@ mixin ApiSerializable {
 
 // 发送请求，并响应
 // 返回序列化后的响应数据
+// onProgress: 服务上报的处理进度
+// timeout: 超时时间，为空时使用服务默认超时，为0时不限制
+// cancelToken: 取消标识，取消后以错误结束
 Future<Uint8List> sendRequest<T extends ApiSerializable >(
-    String service, String func, T? request) {
+    String service, String func, T? request,
+    {void Function(ProgressMsg)? onProgress,
+    Duration? timeout,
+    CancelToken? cancelToken}) {
   // 序列号
   final id = _seq++;
   // 记录发送信息
   Completer<Uint8List> completer = Completer();
   _reqMap[id] = completer;
+  if (onProgress != null) {
+    _progressMap[id] = onProgress;
+  }
   // 发送
   BaseRequest(
     id: id,
     service: service,
     func: func,
     isStream: false,
+    isCancel: false,
+    timeout: timeout?.inMilliseconds,
   ).sendSignalToRust(request?.bincodeSerialize() ?? Uint8List(0));
+  _bindCancel(id, cancelToken);
   // 返回
   return completer.future;
 }
@@ -68,8 +147,11 @@ Future<Uint8List> sendEmptyRequest(String service, String func) {
 }
 
 // 发送请求，并流式响应
+// timeout: 超时时间，为空时使用服务默认超时，为0时不限制
+// cancelToken: 取消标识，取消后以错误结束
 Stream<Uint8List> sendRequestStream<T extends ApiSerializable>(
-    String service, String func, T? request) {
+    String service, String func, T? request,
+    {Duration? timeout, CancelToken? cancelToken}) {
   // 序列号
   final id = _seq++;
   // 记录发送信息
@@ -81,11 +163,45 @@ Stream<Uint8List> sendRequestStream<T extends ApiSerializable>(
     service: service,
     func: func,
     isStream: true,
+    isCancel: false,
+    timeout: timeout?.inMilliseconds,
   ).sendSignalToRust(request?.bincodeSerialize() ?? Uint8List(0));
+  _bindCancel(id, cancelToken);
   // 返回
   return controller.stream;
 }
 
+// 取消进行中的请求
+// 后端会以取消标识结束对应请求
+void cancelRequest(int id) {
+  BaseRequest(
+    id: id,
+    service: "",
+    func: "",
+    isStream: false,
+    isCancel: true,
+    timeout: null,
+  ).sendSignalToRust(Uint8List(0));
+}
+
+// 关联请求与取消标识 标识已取消时立即取消
+void _bindCancel(int id, CancelToken? token) {
+  if (token == null) {
+    return;
+  }
+  if (token.isCancelled) {
+    cancelRequest(id);
+    return;
+  }
+  token._ids.add(id);
+  _cancelMap[id] = token;
+}
+
+// 请求结束 解除与取消标识的关联
+void _releaseCancel(int id) {
+  _cancelMap.remove(id)?._ids.remove(id);
+}
+
 // 处理流式响应
 void _handleStream(RustSignalPack<BaseResponse> data) {
   final rsp = data.message;
@@ -97,11 +213,13 @@ void _handleStream(RustSignalPack<BaseResponse> data) {
   if (rsp.msg.isNotEmpty) {
     // 错误：处理错误
     // error(rsp.msg);
-    controller.addError(rsp.msg);
+    controller.addError(ApiException.fromResponse(rsp));
   } else if (data.binary.isNotEmpty) {
     controller.add(data.binary);
   }
   if (rsp.isEnd) {
+    _releaseCancel(rsp.id);
+    _reqStreamMap.remove(rsp.id);
     controller.close();
   }
 }
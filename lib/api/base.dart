@@ -1,3 +1,4 @@
+import 'dart:typed_data';
 
 import 'package:nftools/common/constants.dart';
 
@@ -31,4 +32,17 @@ Future<void> setRouterEnabled(String router, bool enabled) async {
 Future<void> enableService(String service) async {
   await sendRequest<StringMsg>
     (_service, "enableService", StringMsg(value: service));
-}
\ No newline at end of file
+}
+// 订阅事件主题 返回该主题的事件数据
+Stream<Uint8List> subscribeEvent(String topic) {
+  sendRequest<StringMsg>(_service, "subscribe", StringMsg(value: topic));
+  return BaseEvent.rustSignalStream
+      .where((e) => e.message.topic == topic)
+      .map((e) => e.binary);
+}
+
+// 取消订阅事件主题
+Future<void> unsubscribeEvent(String topic) async {
+  await sendRequest<StringMsg>
+    (_service, "unsubscribe", StringMsg(value: topic));
+}
@@ -11,11 +11,28 @@ const String _getAccount = "get_account";
 const String _setAccount = "set_account";
 const String _addSyncDir = "add_sync_dir";
 const String _syncDir = "sync_dir";
+const String _previewSyncDir = "preview_sync_dir";
+const String _applySyncDir = "apply_sync_dir";
 const String _deleteLocalDir = "del_local_dir";
 const String _addLocalDir = "add_local_file";
 const String _deleteRemoteDir = "del_remote_dir";
 const String _setTimer = "set_timer";
 const String _getTimer = "get_timer";
+const String _getSyncRuns = "get_sync_runs";
+const String _setSyncConfig = "set_sync_config";
+const String _getSyncConfig = "get_sync_config";
+const String _listTrash = "list_trash";
+const String _restoreTrash = "restore_trash";
+const String _purgeTrash = "purge_trash";
+const String _listRemoteVersions = "list_remote_versions";
+const String _restoreRemoteVersion = "restore_remote_version";
+const String _listAccounts = "list_accounts";
+const String _addAccount = "add_account";
+const String _updateAccount = "update_account";
+const String _removeAccount = "remove_account";
+const String _testAccount = "test_account";
+const String _setIncludes = "set_includes";
+const String _getIncludes = "get_includes";
 
 Future<ListFileMsg> listDirs() async {
   var data = await sendRequest<EmptyMsg>(_service, _listDirs, null);
@@ -56,15 +73,46 @@ Future<WebDavConfigMsg> getAccount() async {
   return result;
 }
 
-Future<FileMsg> addSyncDir(String localDir, String tag) async {
+Future<FileMsg> addSyncDir(String localDir, String tag,
+    {List<String> ignore = const [],
+    String passphrase = "",
+    bool encryptNames = false,
+    String account = ""}) async {
   final data = await sendRequest(
-      _service, _addSyncDir, AddSyncDirMsg(localDir: localDir, tag: tag));
+      _service,
+      _addSyncDir,
+      AddSyncDirMsg(
+          localDir: localDir,
+          tag: tag,
+          ignore: ignore,
+          passphrase: passphrase,
+          encryptNames: encryptNames,
+          account: account));
   return FileMsg.bincodeDeserialize(data);
 }
 
-Future<SyncFileDetailMsg> syncDir(String remoteId) async {
+Future<SyncFileDetailMsg> syncDir(String remoteId,
+    {CancelToken? cancelToken}) async {
+  final data = await sendRequest(
+      _service, _syncDir, StringMsg(value: remoteId),
+      cancelToken: cancelToken);
+  return SyncFileDetailMsg.bincodeDeserialize(data);
+}
+
+// 预览同步计划
+Future<SyncPreviewMsg> previewSyncDir(String remoteId) async {
   final data =
-      await sendRequest(_service, _syncDir, StringMsg(value: remoteId));
+      await sendRequest(_service, _previewSyncDir, StringMsg(value: remoteId));
+  return SyncPreviewMsg.bincodeDeserialize(data);
+}
+
+// 按预览结果同步 跳过排除的文件
+Future<SyncFileDetailMsg> applySyncDir(
+    String remoteId, List<String> exclude,
+    {CancelToken? cancelToken}) async {
+  final data = await sendRequest(_service, _applySyncDir,
+      ApplySyncDirMsg(remoteDir: remoteId, exclude: exclude),
+      cancelToken: cancelToken);
   return SyncFileDetailMsg.bincodeDeserialize(data);
 }
 
@@ -72,9 +120,19 @@ void deleteLocalDir(String localDir) async {
   await sendRequest(_service, _deleteLocalDir, StringMsg(value: localDir));
 }
 
-Future<FileMsg> addLocalDir(String localDir, String remoteId) async {
-  final data = await sendRequest(_service, _addLocalDir,
-      AddLocalForRemoteMsg(localDir: localDir, remoteDir: remoteId));
+Future<FileMsg> addLocalDir(String localDir, String remoteId,
+    {String passphrase = "",
+    String account = "",
+    List<String> includes = const []}) async {
+  final data = await sendRequest(
+      _service,
+      _addLocalDir,
+      AddLocalForRemoteMsg(
+          localDir: localDir,
+          remoteDir: remoteId,
+          passphrase: passphrase,
+          account: account,
+          includes: includes));
   return FileMsg.bincodeDeserialize(data);
 }
 
@@ -93,3 +151,95 @@ Future<int> getTimer() async {
   var result = UintFiveMsg.bincodeDeserialize(data);
   return result.value;
 }
+
+// 获取定时同步记录
+Future<List<SyncRunMsg>> getSyncRuns() async {
+  var data = await sendRequest<EmptyMsg>(_service, _getSyncRuns, null);
+  return SyncRunsMsg.bincodeDeserialize(data).runs;
+}
+
+// 设置同步配置
+Future<void> setSyncConfig(SyncConfigMsg config) async {
+  await sendRequest(_service, _setSyncConfig, config);
+}
+
+// 获取同步配置
+Future<SyncConfigMsg> getSyncConfig() async {
+  var data = await sendRequest<EmptyMsg>(_service, _getSyncConfig, null);
+  return SyncConfigMsg.bincodeDeserialize(data);
+}
+
+// 获取回收站文件列表 remoteId为空时返回全部
+Future<List<SyncTrashItemMsg>> listTrash([String remoteId = ""]) async {
+  var data =
+      await sendRequest(_service, _listTrash, StringMsg(value: remoteId));
+  return SyncTrashMsg.bincodeDeserialize(data).items;
+}
+
+// 还原回收站中的文件
+Future<void> restoreTrash(String id) async {
+  await sendRequest(_service, _restoreTrash, StringMsg(value: id));
+}
+
+// 永久删除回收站中的文件 id为空时清空
+Future<void> purgeTrash([String id = ""]) async {
+  await sendRequest(_service, _purgeTrash, StringMsg(value: id));
+}
+
+// 获取远端历史版本 file为空时返回全部
+Future<List<RemoteVersionMsg>> listRemoteVersions(String remoteId,
+    [String file = ""]) async {
+  var data = await sendRequest(_service, _listRemoteVersions,
+      RemoteVersionReqMsg(remoteDir: remoteId, file: file));
+  return RemoteVersionsMsg.bincodeDeserialize(data).versions;
+}
+
+// 还原远端历史版本
+Future<void> restoreRemoteVersion(
+    String remoteId, RemoteVersionMsg version) async {
+  await sendRequest(
+      _service,
+      _restoreRemoteVersion,
+      RestoreRemoteVersionMsg(
+          remoteDir: remoteId, file: version.file, time: version.time));
+}
+
+// 同步账户列表
+Future<SyncAccountsMsg> listAccounts() async {
+  var data = await sendRequest<EmptyMsg>(_service, _listAccounts, null);
+  return SyncAccountsMsg.bincodeDeserialize(data);
+}
+
+// 新增同步账户
+Future<void> addAccount(SyncAccountMsg account) async {
+  await sendRequest(_service, _addAccount, account);
+}
+
+// 修改同步账户
+Future<void> updateAccount(SyncAccountMsg account) async {
+  await sendRequest(_service, _updateAccount, account);
+}
+
+// 删除同步账户
+Future<void> removeAccount(String name) async {
+  await sendRequest(_service, _removeAccount, StringMsg(value: name));
+}
+
+// 测试同步账户连接
+Future<bool> testAccount(SyncAccountMsg account) async {
+  final data = await sendRequest(_service, _testAccount, account);
+  return BoolMsg.bincodeDeserialize(data).value;
+}
+
+// 设定本机选择同步的子文件夹 为空时同步全部
+Future<void> setSyncIncludes(String remoteId, List<String> includes) async {
+  await sendRequest(_service, _setIncludes,
+      SyncIncludesMsg(remoteDir: remoteId, includes: includes));
+}
+
+// 获取本机选择同步的子文件夹
+Future<List<String>> getSyncIncludes(String remoteId) async {
+  final data =
+      await sendRequest(_service, _getIncludes, StringMsg(value: remoteId));
+  return SyncIncludesMsg.bincodeDeserialize(data).includes;
+}
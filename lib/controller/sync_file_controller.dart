@@ -1,6 +1,7 @@
 import 'dart:async';
 
 import 'package:get/get.dart';
+import 'package:nftools/api/api.dart';
 import 'package:nftools/api/syncfile.dart' as $api;
 import 'package:nftools/state/sync_file_state.dart';
 import 'package:nftools/utils/log.dart';
@@ -13,6 +14,12 @@ class SyncFileController extends GetxController with GetxUpdateMixin {
 
   Timer? _timer;
 
+  // 进行中同步的取消标识
+  CancelToken? _syncToken;
+
+  // 是否正在同步
+  bool get isSyncing => _syncToken != null;
+
   @override
   void onReady() {
     _init();
@@ -47,6 +54,15 @@ class SyncFileController extends GetxController with GetxUpdateMixin {
       state.userController.text = accountInfo.account;
       state.passwdController.text = accountInfo.passwd;
       state.accountInfoLock = true;
+    } on ApiException catch (e) {
+      switch (e.category) {
+        case ErrorCategoryMsg.auth:
+          info("无登录信息或登录失败，请先登录");
+        case ErrorCategoryMsg.network:
+          error("无法连接同步服务器: ${e.message}");
+        default:
+          error(e.message);
+      }
     } finally {
       state.isLoading = false;
       update();
@@ -104,10 +120,13 @@ class SyncFileController extends GetxController with GetxUpdateMixin {
 
   // 同步一个文件夹
   Future<SyncFileDetailMsg> syncDir(String remoteId) async {
+    // 定时同步时沿用整批同步的取消标识
+    final owner = _syncToken == null;
+    final token = _syncToken ??= CancelToken();
     state.isLoading = true;
     update();
     try {
-      final result = await $api.syncDir(remoteId);
+      final result = await $api.syncDir(remoteId, cancelToken: token);
       for (int i = 0; i < state.fileList.length; i++) {
         if (state.fileList[i].remoteDir == remoteId) {
           state.fileList[i] = FileMsg(
@@ -117,17 +136,33 @@ class SyncFileController extends GetxController with GetxUpdateMixin {
             modify: 0,
             add: 0,
             del: 0,
+            conflict: 0,
+            encrypted: state.fileList[i].encrypted,
+            account: state.fileList[i].account,
             status: FileStatusEnumMsg.synced,
           );
         }
       }
       return result;
+    } catch (e) {
+      if (token.isCancelled) {
+        info("已取消同步");
+      }
+      rethrow;
     } finally {
+      if (owner) {
+        _syncToken = null;
+      }
       state.isLoading = false;
       update();
     }
   }
 
+  // 取消进行中的同步 未完成的同步在下次同步时恢复
+  void cancelSync() {
+    _syncToken?.cancel();
+  }
+
   // 删除一个本地文件夹记录
   void deleteLocalDir(String localDir) {
     $api.deleteLocalDir(localDir);
@@ -182,13 +217,18 @@ class SyncFileController extends GetxController with GetxUpdateMixin {
         state.isLoading = true;
         update();
         await listFiles();
-        for (var value in state.fileList) {
-          if (value.localDir.isEmpty ||
-              value.remoteDir.isEmpty ||
-              value.status == FileStatusEnumMsg.synced) {
-            continue;
+        _syncToken = CancelToken();
+        try {
+          for (var value in state.fileList) {
+            if (value.localDir.isEmpty ||
+                value.remoteDir.isEmpty ||
+                value.status == FileStatusEnumMsg.synced) {
+              continue;
+            }
+            await syncDir(value.remoteDir);
           }
-          await syncDir(value.remoteDir);
+        } finally {
+          _syncToken = null;
         }
         state.isLoading = false;
         update();
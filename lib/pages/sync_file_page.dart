@@ -4,6 +4,7 @@ import 'package:file_picker/file_picker.dart';
 import 'package:fluent_ui/fluent_ui.dart';
 import 'package:get/get.dart';
 import 'package:go_router/go_router.dart';
+import 'package:nftools/api/api.dart';
 import 'package:nftools/common/style.dart';
 import 'package:nftools/controller/sync_file_controller.dart';
 import 'package:nftools/utils/log.dart';
@@ -299,6 +300,14 @@ class SyncFilePage extends StatelessWidget {
                       : () {
                           logic.refreshList();
                         }),
+              CommandBarButton(
+                  icon: const Icon(FluentIcons.cancel),
+                  label: const Text("停止同步"),
+                  onPressed: logic.isSyncing
+                      ? () {
+                          logic.cancelSync();
+                        }
+                      : null),
               CommandBarButton(
                 icon: const Icon(FluentIcons.timer),
                 label: logic.state.timer == 0
@@ -358,6 +367,12 @@ class _DataSource extends NFDataTableSource {
         case FileStatusEnumMsg.upload:
           desc = "待上传";
           break;
+        case FileStatusEnumMsg.mixed:
+          desc = "待同步";
+          break;
+        case FileStatusEnumMsg.conflict:
+          desc = "存在冲突";
+          break;
       }
       return Text(
         desc,
@@ -409,7 +424,7 @@ class _DataSource extends NFDataTableSource {
             onPressed: file.status == FileStatusEnumMsg.synced
                 ? null
                 : () async {
-                    await logic.syncDir(file.remoteDir);
+                    await _syncDir(context, logic, file.remoteDir);
                   },
             child: const Text("同步")),
         NFLayout.hlineh3,
@@ -426,6 +441,34 @@ class _DataSource extends NFDataTableSource {
   }
 }
 
+// 同步文件夹 按错误类型提示，被其它设备锁定时可重试
+Future<void> _syncDir(
+    BuildContext context, SyncFileController logic, String remoteDir) async {
+  try {
+    await logic.syncDir(remoteDir);
+  } on ApiException catch (e) {
+    switch (e.code) {
+      case ErrorCode.cancelled:
+        return;
+      case ErrorCode.locked:
+        if (context.mounted &&
+            await confirmDialog(context, "文件夹正在同步", "${e.message}\n是否重试?")) {
+          if (context.mounted) {
+            await _syncDir(context, logic, remoteDir);
+          }
+        }
+      case ErrorCode.notLoggedIn:
+        error("账户认证失败，请检查账户设置");
+      case ErrorCode.network:
+        error("网络连接失败: ${e.message}");
+      case ErrorCode.timeout:
+        error("同步超时: ${e.message}");
+      default:
+        error("同步失败: ${e.message}");
+    }
+  }
+}
+
 // 获取一个本地文件夹路径
 Future<String?> _addLocalDir() async {
   String? directoryPath = await FilePicker.platform.getDirectoryPath();
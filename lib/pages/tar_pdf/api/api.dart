@@ -23,8 +23,8 @@ const String _similarPdf = "similar_pdf";
 
 
 // start
-Stream<TarPdfMsg> handle(List<String> pdfFiles) {
-  var stream = sendRequestStream(_service, _handle, VecStringMsg(values: pdfFiles));
+Stream<TarPdfMsg> handle(List<String> pdfFiles, {CancelToken? cancelToken}) {
+  var stream = sendRequestStream(_service, _handle, VecStringMsg(values: pdfFiles), cancelToken: cancelToken);
   return stream.map((x) => TarPdfMsg.bincodeDeserialize(x));
 }
 
@@ -10,6 +10,7 @@ import 'package:nftools/utils/log.dart';
 import 'package:nftools/utils/utils.dart';
 import 'package:pasteboard/pasteboard.dart';
 
+import '../../../api/api.dart';
 import '../api/api.dart' as $api;
 
 class TarPdfController extends GetxController with GetxUpdateMixin {
@@ -18,6 +19,9 @@ class TarPdfController extends GetxController with GetxUpdateMixin {
   // 文本框渲染原始数据
   List<OcrDataMsg> _originalOcrDatas = [];
 
+  // 批量识别的取消标识
+  CancelToken? _handleToken;
+
 
   @override
   void onReady() {
@@ -179,7 +183,8 @@ class TarPdfController extends GetxController with GetxUpdateMixin {
     }
 
     // 开始处理
-    final Stream<TarPdfMsg> stream = $api.handle(state.pdfFiles);
+    final token = _handleToken = CancelToken();
+    final Stream<TarPdfMsg> stream = $api.handle(state.pdfFiles, cancelToken: token);
     state.processEnum = state.processEnum.next() ?? state.processEnum;
     update();
     stream.listen((data) {
@@ -188,15 +193,29 @@ class TarPdfController extends GetxController with GetxUpdateMixin {
       state.currentFile = data.currentFile;
       update();
     }, onDone: () {
+      _handleToken = null;
+      if (token.isCancelled) {
+        return;
+      }
       info("处理完成");
       _getResult();
     }, onError: (e) {
-      error("处理失败: $e");
+      _handleToken = null;
+      if (token.isCancelled) {
+        info("已取消处理");
+      } else {
+        error("处理失败: $e");
+      }
       state.processEnum = state.processEnum.pre() ?? state.processEnum;
       update();
     }, cancelOnError: true);
   }
 
+  // 取消批量识别
+  void cancelHandle() {
+    _handleToken?.cancel();
+  }
+
   void _getResult() async {
     state.ocrResults = await $api.getOcrPdfData();
     state.processEnum = state.processEnum.next() ?? state.processEnum;
@@ -303,6 +303,11 @@ class TarPdfPage extends StatelessWidget {
                         value: logic.state.sum == 0
                             ? 0
                             : logic.state.current / logic.state.sum * 100)),
+                Button(
+                    child: const Text("取消"),
+                    onPressed: () {
+                      logic.cancelHandle();
+                    }),
               ])),
       Expanded(
         flex: 2,
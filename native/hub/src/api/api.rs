@@ -1,15 +1,25 @@
-use crate::service::service::{ImmService, LazyService, Service, StreamService};
-use crate::{async_func_typeno, async_func_typetype, func_end, service_handle};
-use ahash::AHashMap;
-use log::{error, info};
+use crate::service;
+use crate::service::service::{ImmService, LazyService, Progress, Service, ServiceDescriptor, StreamService};
+use crate::{async_func_typeno, async_func_typetype, func_end, func_notype, func_typeno, service_handle};
+use ahash::{AHashMap, AHashSet};
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use log::{error, info, warn};
 use rinf::{DartSignalPack, RustSignalBinary};
+use std::future::Future;
 use std::ops::DerefMut;
 use std::sync::Arc;
-use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc::unbounded_channel;
 use tokio::sync::Mutex;
-use crate::api::{BaseRequest, BaseResponse};
+use tokio::task::AbortHandle;
+use crate::api::{BaseEvent, BaseRequest, BaseResponse};
+use crate::common::error::ServiceError;
+use crate::common::event_bus::topic;
 use crate::common::global_data::GlobalData;
-use crate::messages::common::{BoolMsg, StringMsg};
+use crate::messages::api::{FuncInfoMsg, ServiceInfoMsg, ServiceListMsg};
+use crate::messages::common::{BoolMsg, ProgressMsg, StringMsg, VecStringMsg};
 use crate::service::ai::BaiduAiService;
 use crate::service::display::display_os::{DisplayLight, DisplayMode};
 use crate::service::img::img_split::ImageSplitService;
@@ -37,23 +47,118 @@ enum StreamServiceEnum {
     StreamService(Arc<Mutex<Box<dyn StreamService>>>),
 }
 
+impl ServiceEnum {
+    /// 关闭服务 等待进行中的请求结束后执行
+    async fn close(&self) -> anyhow::Result<()> {
+        match self {
+            ServiceEnum::LazyService(service) => {
+                let mut s = service.lock().await;
+                if !s.1 {
+                    s.0.close().await?;
+                }
+            }
+            ServiceEnum::Service(service) => {
+                service.lock().await.close().await?;
+            }
+            ServiceEnum::ImmService(service) => {
+                service.close().await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl StreamServiceEnum {
+    /// 关闭服务 等待进行中的请求结束后执行
+    async fn close(&self) -> anyhow::Result<()> {
+        match self {
+            StreamServiceEnum::StreamService(service) => {
+                service.lock().await.close().await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 进行中的请求任务
+struct RunningTask {
+    // 任务句柄 用于取消
+    handle: AbortHandle,
+    // 所属服务
+    service: String,
+    // 是否为流式请求
+    is_stream: bool,
+}
+
+/// 请求任务信息
+struct TaskInfo {
+    // 请求id
+    id: u32,
+    // 所属服务
+    service: String,
+    // 是否为流式请求
+    is_stream: bool,
+    // 超时时间
+    timeout: Option<Duration>,
+}
+
 /// 服务分发
 pub struct ApiService {
     services: AHashMap<&'static str, ServiceEnum>,
     stream_services: AHashMap<&'static str, StreamServiceEnum>,
+    // 服务的只读处理器 只读方法不需要等待服务锁
+    read_services: AHashMap<&'static str, Arc<Box<dyn ImmService>>>,
     global_data: GlobalData,
+    // 进行中的请求 k: 请求id
+    tasks: Arc<std::sync::Mutex<AHashMap<u32, RunningTask>>>,
+    // 前端已订阅的事件主题
+    subscriptions: Arc<std::sync::RwLock<AHashSet<&'static str>>>,
+    // 事件推送任务
+    event_task: AbortHandle,
 }
 
 impl ApiService {
     /// new
     pub fn new(global_data: GlobalData) -> Self {
+        let subscriptions = Arc::new(std::sync::RwLock::new(AHashSet::new()));
+        let event_task = Self::forward_events(&global_data, subscriptions.clone());
         ApiService {
             services: AHashMap::new(),
             stream_services: AHashMap::new(),
-            global_data
+            read_services: AHashMap::new(),
+            global_data,
+            tasks: Arc::new(std::sync::Mutex::new(AHashMap::new())),
+            subscriptions,
+            event_task,
         }
     }
 
+    /// 推送已订阅主题的事件
+    fn forward_events(
+        global_data: &GlobalData,
+        subscriptions: Arc<std::sync::RwLock<AHashSet<&'static str>>>,
+    ) -> AbortHandle {
+        let mut rx = global_data.event_bus().subscribe();
+        tokio::spawn(async move {
+            loop {
+                match rx.recv().await {
+                    Ok(event) => {
+                        if !subscriptions.read().unwrap().contains(event.topic) {
+                            continue;
+                        }
+                        BaseEvent {
+                            topic: event.topic.to_string(),
+                        }
+                        .send_signal_to_dart(event.data);
+                    }
+                    Err(RecvError::Lagged(count)) => warn!("事件推送不及时，丢弃{}个事件", count),
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        })
+        .abort_handle()
+    }
+
     /// 新增服务
     fn add_service(&mut self, service: Box<dyn Service>, name: &'static str) {
         self.services.insert(
@@ -93,7 +198,13 @@ impl ApiService {
             self.handle_stream(signal);
             return;
         }
-        
+
+        // 只读方法处理
+        if let Some(reader) = self.read_handler(&signal.message) {
+            self.imm_service_handle(reader, signal);
+            return;
+        }
+
         // 一般服务处理
         let Some(service) = self.services.get(signal.message.service.as_str()) else {
             // 如果一般性服务也没有，则再次查找stream服务
@@ -101,13 +212,14 @@ impl ApiService {
                 None => {
                     generate_error_response(
                         signal.message.id,
-                        format!("未知服务 {}", signal.message.service),
+                        ServiceError::NotFound(format!("未知服务 {}", signal.message.service)),
+                        None,
                         false,
                     )
                     .send_signal_to_dart(Vec::with_capacity(0));
                 }
                 Some(service) => {
-                    Self::handle_stream_service_for_service(service, signal);
+                    self.handle_stream_service_for_service(service, signal);
                 }
             }
             return;
@@ -116,53 +228,69 @@ impl ApiService {
         // 执行服务
         match service {
             ServiceEnum::LazyService(service) => {
-                Self::lazy_service_handle(service.clone(), signal);
+                self.lazy_service_handle(service.clone(), signal);
             }
             ServiceEnum::Service(service) => {
-                Self::service_handle(service.clone(), signal);
+                self.service_handle(service.clone(), signal);
             }
             ServiceEnum::ImmService(service) => {
-                Self::imm_service_handle(service.clone(), signal);
+                self.imm_service_handle(service.clone(), signal);
             }
         };
     }
 
+    /// 取消进行中的请求
+    /// 中断对应任务(释放其持有的服务锁)，并发送取消响应
+    pub fn cancel(&self, id: u32) {
+        let Some(task) = self.tasks.lock().unwrap().remove(&id) else {
+            info!("请求{}不存在或已结束，无需取消", id);
+            return;
+        };
+        task.handle.abort();
+        BaseResponse {
+            id,
+            msg: ServiceError::Cancelled.message().to_string(),
+            is_stream: task.is_stream,
+            is_end: true,
+            is_cancel: true,
+            is_timeout: false,
+            error: Some(ServiceError::Cancelled.to_msg(None)),
+        }
+        .send_signal_to_dart(Vec::with_capacity(0));
+    }
+
+    /// 取消服务所有进行中的请求
+    fn cancel_service(&self, service: &str) {
+        let ids: Vec<u32> = self
+            .tasks
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, task)| task.service == service)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in ids {
+            self.cancel(id);
+        }
+    }
+
     pub async fn close(self, signal: DartSignalPack<BaseRequest>) -> BaseResponse{
+        self.event_task.abort();
         let mut handles = Vec::new();
         // 关闭所有服务
         for (desc, service) in self.services {
-            let handle = match service {
-                ServiceEnum::LazyService(service) => tokio::spawn(async move {
-                    let mut s = service.lock().await;
-                    if !s.1 {
-                        if let Err(e) = s.0.close().await {
-                            error!("关闭服务{}失败 {}", desc, e);
-                        }
-                    }
-                }),
-                ServiceEnum::Service(service) => tokio::spawn(async move {
-                    let mut s = service.lock().await;
-                    if let Err(e) = s.close().await {
-                        error!("关闭服务{}失败 {}", desc, e);
-                    }
-                }),
-                ServiceEnum::ImmService(service) => tokio::spawn(async move {
-                    if let Err(e) = service.close().await {
-                        error!("关闭服务{}失败 {}", desc, e);
-                    }
-                }),
-            };
-            handles.push(handle);
+            handles.push(tokio::spawn(async move {
+                if let Err(e) = service.close().await {
+                    error!("关闭服务{}失败 {}", desc, e);
+                }
+            }));
         }
         for (desc, service) in self.stream_services {
-            let handle = match service {
-                StreamServiceEnum::StreamService(service) => tokio::spawn(async move {
-                    if let Err(e) = service.lock().await.close().await {
-                        error!("关闭服务{}失败 {}", desc, e);
-                    }
-                }),
-            };
-            handles.push(handle);
+            handles.push(tokio::spawn(async move {
+                if let Err(e) = service.close().await {
+                    error!("关闭服务{}失败 {}", desc, e);
+                }
+            }));
         }
 
         // 等待任务全部结束
@@ -172,96 +300,202 @@ impl ApiService {
             id: signal.message.id,
             msg: String::with_capacity(0),
             is_stream: false,
-            is_end: false,
+            is_end: true,
+            is_cancel: false,
+            is_timeout: false,
+            error: None,
+        }
+    }
+
+    /// 启动请求任务，并记录任务句柄以便取消
+    /// 任务结束时，若未被取消，则发送最终响应
+    /// 超过超时时间未完成的任务会被中断(释放其持有的服务锁)，并发送超时响应
+    fn spawn_task<F>(&self, task: TaskInfo, future: F)
+    where
+        F: Future<Output = (BaseResponse, Vec<u8>)> + Send + 'static,
+    {
+        let TaskInfo {
+            id,
+            service,
+            is_stream,
+            timeout,
+        } = task;
+        let tasks = self.tasks.clone();
+        // 持有锁直到句柄登记完成，避免任务先于登记结束
+        let mut running = self.tasks.lock().unwrap();
+        let handle = tokio::spawn(async move {
+            let (response, data) = match timeout {
+                None => future.await,
+                Some(timeout) => match tokio::time::timeout(timeout, future).await {
+                    Ok(r) => r,
+                    Err(_) => (
+                        generate_timeout_response(id, timeout, is_stream),
+                        Vec::with_capacity(0),
+                    ),
+                },
+            };
+            // 已被取消的请求由cancel发送响应
+            if tasks.lock().unwrap().remove(&id).is_some() {
+                response.send_signal_to_dart(data);
+            }
+        });
+        running.insert(
+            id,
+            RunningTask {
+                handle: handle.abort_handle(),
+                service,
+                is_stream,
+            },
+        );
+    }
+
+    /// 获取请求对应的只读处理器
+    /// 仅描述中标记为只读的方法使用
+    fn read_handler(&self, request: &BaseRequest) -> Option<Arc<Box<dyn ImmService>>> {
+        let reader = self.read_services.get(request.service.as_str())?;
+        let read_only = SERVICE_REGISTRY
+            .iter()
+            .find(|entry| entry.descriptor.name == request.service)?
+            .descriptor
+            .funcs
+            .iter()
+            .any(|f| f.read_only && f.name == request.func);
+        read_only.then(|| reader.clone())
+    }
+
+    /// 获取请求的任务信息
+    /// 超时时间优先使用请求中的超时时间，其次为方法的超时设置，否则使用服务默认超时时间
+    fn task_info(&self, request: &BaseRequest) -> TaskInfo {
+        let timeout = match request.timeout {
+            Some(0) => None,
+            Some(timeout) => Some(Duration::from_millis(timeout as u64)),
+            None => SERVICE_REGISTRY
+                .iter()
+                .find(|entry| entry.descriptor.name == request.service)
+                .and_then(|entry| {
+                    let descriptor = &entry.descriptor;
+                    match descriptor.funcs.iter().find(|f| f.name == request.func) {
+                        Some(func) => func.timeout.resolve(descriptor.timeout),
+                        None => descriptor.timeout,
+                    }
+                }),
+        };
+        TaskInfo {
+            id: request.id,
+            service: request.service.clone(),
+            is_stream: request.is_stream,
+            timeout,
         }
     }
 
+
     /// 流式服务处理
     fn handle_stream(&self, signal: DartSignalPack<BaseRequest>) {
         let Some(service) = self.stream_services.get(signal.message.service.as_str()) else {
             generate_error_response(
                 signal.message.id,
-                format!("未知服务 {}", signal.message.service),
+                ServiceError::NotFound(format!("未知服务 {}", signal.message.service)),
+                None,
                 true,
             )
             .send_signal_to_dart(Vec::with_capacity(0));
             return;
         };
 
-        let (tx, mut rx) = unbounded_channel::<anyhow::Result<Option<Vec<u8>>>>();
-
-        // 接受并发送响应流
-        let id = signal.message.id;
-        let msg_service = signal.message.service.clone();
-        let func = signal.message.func.clone();
-        tokio::spawn(async move {
-            while let Some(result) = rx.recv().await {
-                match result {
-                    Ok(r) => {
-                        BaseResponse {
-                            id,
-                            msg: String::with_capacity(0),
-                            is_stream: true,
-                            is_end: false,
-                        }
-                        .send_signal_to_dart(r.unwrap_or(Vec::with_capacity(0)));
-                    }
-                    Err(r) => {
-                        let msg = if cfg!(debug_assertions) {
-                            format!("处理请求错误{}-{}:{}", msg_service, func, r)
-                        } else {
-                            r.to_string()
-                        };
-                        BaseResponse {
-                            id,
-                            msg,
-                            is_stream: true,
-                            is_end: false,
-                        }
-                        .send_signal_to_dart(Vec::with_capacity(0));
-                    }
-                }
-            }
-            // 管道关闭后，发送结束标识
-            BaseResponse {
-                id,
-                msg: String::with_capacity(0),
-                is_stream: true,
-                is_end: true,
-            }
-            .send_signal_to_dart(Vec::with_capacity(0));
-        });
-
         match service {
             StreamServiceEnum::StreamService(service) => {
-                Self::stream_service_handle(service.clone(), signal, tx);
+                self.stream_service_handle(service.clone(), signal);
             }
         }
     }
 
     fn stream_service_handle(
+        &self,
         service: Arc<Mutex<Box<dyn StreamService>>>,
         signal: DartSignalPack<BaseRequest>,
-        tx: UnboundedSender<anyhow::Result<Option<Vec<u8>>>>,
     ) {
-        tokio::spawn(async move {
-            let mut service = service.lock().await;
-            let tx_clone = tx.clone();
-            if let Err(e) = service
-                .handle_stream(&signal.message.func, signal.binary, tx)
-                .await
-            {
-                tx_clone.send(Err(e)).unwrap();
-            }
+        let (tx, mut rx) = unbounded_channel::<anyhow::Result<Option<Vec<u8>>>>();
+        let id = signal.message.id;
+        let task = self.task_info(&signal.message);
+        self.spawn_task(task, async move {
+            // 执行服务
+            let msg_service = signal.message.service.clone();
+            let func = signal.message.func.clone();
+            let work = async move {
+                let mut service = service.lock().await;
+                let tx_clone = tx.clone();
+                if let Err(e) = service
+                    .handle_stream(&signal.message.func, signal.binary, tx)
+                    .await
+                {
+                    tx_clone.send(Err(e)).unwrap();
+                }
+            };
+
+            // 接受并发送响应流
+            let forward = async move {
+                while let Some(result) = rx.recv().await {
+                    match result {
+                        Ok(r) => {
+                            BaseResponse {
+                                id,
+                                msg: String::with_capacity(0),
+                                is_stream: true,
+                                is_end: false,
+                                is_cancel: false,
+                                is_timeout: false,
+                                error: None,
+                            }
+                            .send_signal_to_dart(r.unwrap_or(Vec::with_capacity(0)));
+                        }
+                        Err(r) => {
+                            let error = ServiceError::from_anyhow(&r);
+                            BaseResponse {
+                                id,
+                                msg: error.message().to_string(),
+                                is_stream: true,
+                                is_end: false,
+                                is_cancel: false,
+                                is_timeout: false,
+                                error: Some(error.to_msg(Some(generate_error_detail(
+                                    &msg_service,
+                                    &func,
+                                    &r,
+                                )))),
+                            }
+                            .send_signal_to_dart(Vec::with_capacity(0));
+                        }
+                    }
+                }
+            };
+            tokio::join!(work, forward);
+
+            // 管道关闭后，发送结束标识
+            (
+                BaseResponse {
+                    id,
+                    msg: String::with_capacity(0),
+                    is_stream: true,
+                    is_end: true,
+                    is_cancel: false,
+                    is_timeout: false,
+                    error: None,
+                },
+                Vec::with_capacity(0),
+            )
         });
     }
 
     fn lazy_service_handle(
+        &self,
         service: Arc<Mutex<(Box<dyn LazyService>, bool)>>,
         signal: DartSignalPack<BaseRequest>,
     ) {
-        tokio::spawn(async move {
-            service_handle!(_lazy_handle, service, signal);
+        let task = self.task_info(&signal.message);
+        let (progress, forward) = progress_channel(signal.message.id);
+        self.spawn_task(task, async move {
+            let work = async move { service_handle!(_lazy_handle, service, signal, progress) };
+            tokio::join!(work, forward).0
         });
     }
 
@@ -269,6 +503,7 @@ impl ApiService {
         service: Arc<Mutex<(Box<dyn LazyService>, bool)>>,
         func: &str,
         data: Vec<u8>,
+        progress: Progress,
     ) -> anyhow::Result<Vec<u8>> {
         let mut service = service.lock().await;
         let (service, status) = service.deref_mut();
@@ -278,14 +513,17 @@ impl ApiService {
             service.lazy_init_self().await?;
         }
         Ok(service
-            .handle(func, data)
+            .handle_progress(func, data, progress)
             .await?
             .unwrap_or(Vec::with_capacity(0)))
     }
 
-    fn service_handle(service: Arc<Mutex<Box<dyn Service>>>, signal: DartSignalPack<BaseRequest>) {
-        tokio::spawn(async move {
-            service_handle!(_handle, service, signal);
+    fn service_handle(&self, service: Arc<Mutex<Box<dyn Service>>>, signal: DartSignalPack<BaseRequest>) {
+        let task = self.task_info(&signal.message);
+        let (progress, forward) = progress_channel(signal.message.id);
+        self.spawn_task(task, async move {
+            let work = async move { service_handle!(_handle, service, signal, progress) };
+            tokio::join!(work, forward).0
         });
     }
 
@@ -293,17 +531,19 @@ impl ApiService {
         service: Arc<Mutex<Box<dyn Service>>>,
         func: &str,
         data: Vec<u8>,
+        progress: Progress,
     ) -> anyhow::Result<Vec<u8>> {
         let mut service = service.lock().await;
         Ok(service
-            .handle(func, data)
+            .handle_progress(func, data, progress)
             .await?
             .unwrap_or(Vec::with_capacity(0)))
     }
 
-    fn imm_service_handle(service: Arc<Box<dyn ImmService>>, signal: DartSignalPack<BaseRequest>) {
-        tokio::spawn(async move {
-            service_handle!(_imm_handle, service, signal);
+    fn imm_service_handle(&self, service: Arc<Box<dyn ImmService>>, signal: DartSignalPack<BaseRequest>) {
+        let task = self.task_info(&signal.message);
+        self.spawn_task(task, async move {
+            service_handle!(_imm_handle, service, signal)
         });
     }
 
@@ -322,65 +562,128 @@ impl ApiService {
         service: Arc<Mutex<Box<dyn StreamService>>>,
         func: &str,
         data: Vec<u8>,
+        progress: Progress,
     ) -> anyhow::Result<Vec<u8>> {
         let mut service = service.lock().await;
         Ok(service
-            .handle(func, data)
+            .handle_progress(func, data, progress)
             .await?
             .unwrap_or(Vec::with_capacity(0)))
     }
 
     fn handle_stream_service_for_service(
+        &self,
         service: &StreamServiceEnum,
         signal: DartSignalPack<BaseRequest>,
     ) {
         match service {
             StreamServiceEnum::StreamService(service) => {
                 let service = service.clone();
-                tokio::spawn(async move {
-                    service_handle!(_handle_stream_service, service, signal);
+                let task = self.task_info(&signal.message);
+                let (progress, forward) = progress_channel(signal.message.id);
+                self.spawn_task(task, async move {
+                    let work = async move {
+                        service_handle!(_handle_stream_service, service, signal, progress)
+                    };
+                    tokio::join!(work, forward).0
                 });
             }
         }
     }
 }
 
+/// 生成进度通道
+/// 服务上报的进度以未结束的响应帧发送，服务处理结束后通道关闭
+fn progress_channel(id: u32) -> (Progress, impl Future<Output = ()> + Send + 'static) {
+    let (tx, mut rx) = unbounded_channel::<ProgressMsg>();
+    let forward = async move {
+        while let Some(progress) = rx.recv().await {
+            match rinf::serialize(&progress) {
+                Ok(buf) => BaseResponse {
+                    id,
+                    msg: String::with_capacity(0),
+                    is_stream: false,
+                    is_end: false,
+                    is_cancel: false,
+                    is_timeout: false,
+                    error: None,
+                }
+                .send_signal_to_dart(buf),
+                Err(e) => error!("进度序列化失败: {}", e),
+            }
+        }
+    };
+    (Progress::new(tx), forward)
+}
+
+/// 生成超时响应
+fn generate_timeout_response(id: u32, timeout: Duration, is_stream: bool) -> BaseResponse {
+    let error = ServiceError::Timeout(format!("请求超时: {}ms内未完成", timeout.as_millis()));
+    BaseResponse {
+        id,
+        msg: error.message().to_string(),
+        is_stream,
+        is_end: true,
+        is_cancel: false,
+        is_timeout: true,
+        error: Some(error.to_msg(None)),
+    }
+}
+
 /// 生成错误响应
-fn generate_error_response(id: u32, error: String, is_stream: bool) -> BaseResponse {
+fn generate_error_response(
+    id: u32,
+    error: ServiceError,
+    detail: Option<String>,
+    is_stream: bool,
+) -> BaseResponse {
     BaseResponse {
         id,
-        msg: error,
+        msg: error.message().to_string(),
         is_stream,
         is_end: true,
+        is_cancel: false,
+        is_timeout: false,
+        error: Some(error.to_msg(detail)),
     }
 }
 
+/// 生成错误详情
+fn generate_error_detail(service: &str, func: &str, e: &anyhow::Error) -> String {
+    format!("处理请求错误{}-{}:{:#}", service, func, e)
+}
+
 mod macros {
+    /// 执行服务，并生成响应
     #[macro_export]
     macro_rules! service_handle {
-        ($func: ident, $service: ident, $signal: ident) => {
-            match Self::$func($service, &$signal.message.func, $signal.binary).await {
-                Ok(r) => {
+        ($func: ident, $service: ident, $signal: ident $(, $progress: ident)?) => {
+            match Self::$func($service, &$signal.message.func, $signal.binary $(, $progress)?).await {
+                Ok(r) => (
                     BaseResponse {
                         id: $signal.message.id,
                         msg: String::with_capacity(0),
                         is_stream: false,
                         is_end: true,
-                    }
-                    .send_signal_to_dart(r);
-                }
-                Err(e) => {
-                    let msg = if cfg!(debug_assertions) {
-                        format!(
-                            "处理请求错误{}-{}:{}",
-                            $signal.message.service, $signal.message.func, e
-                        )
-                    } else {
-                        e.to_string()
-                    };
-                    generate_error_response($signal.message.id, msg, false)
-                        .send_signal_to_dart(Vec::with_capacity(0));
-                }
+                        is_cancel: false,
+                        is_timeout: false,
+                        error: None,
+                    },
+                    r,
+                ),
+                Err(e) => (
+                    generate_error_response(
+                        $signal.message.id,
+                        ServiceError::from_anyhow(&e),
+                        Some(generate_error_detail(
+                            &$signal.message.service,
+                            &$signal.message.func,
+                            &e,
+                        )),
+                        false,
+                    ),
+                    Vec::with_capacity(0),
+                ),
             }
         };
     }
@@ -390,21 +693,29 @@ impl ApiService {
     /// api服务处理
     pub async fn api_handle(&mut self, signal: DartSignalPack<BaseRequest>) {
         let id = signal.message.id;
+        let func = signal.message.func.clone();
         match self.inner_handle(signal).await{
             Ok(r) => {
                 BaseResponse {
                     id,
                     msg: String::with_capacity(0),
                     is_stream: false,
-                    is_end: false,
+                    is_end: true,
+                    is_cancel: false,
+                    is_timeout: false,
+                    error: None,
                 }.send_signal_to_dart(r.unwrap_or(Vec::with_capacity(0)));
             }
             Err(e) => {
+                let error = ServiceError::from_anyhow(&e);
                 BaseResponse {
-                    id, 
-                    msg: e.to_string(),
+                    id,
+                    msg: error.message().to_string(),
                     is_stream: false,
-                    is_end: false,
+                    is_end: true,
+                    is_cancel: false,
+                    is_timeout: false,
+                    error: Some(error.to_msg(Some(generate_error_detail("ApiService", &func, &e)))),
                 }.send_signal_to_dart(Vec::with_capacity(0));
             }
         };
@@ -414,81 +725,242 @@ impl ApiService {
         let func = signal.message.func.as_str();
         let data = signal.binary;
         
-        async_func_typetype!(self, func, data, get_router_enabled, StringMessage);
-        async_func_typeno!(self, func, data, enable_service, StringMessage);
+        async_func_typetype!(self, func, data, get_router_enabled, StringMsg, describe_service, StringMsg);
+        async_func_typeno!(self, func, data, enable_service, StringMsg, disable_service, StringMsg);
+        func_typeno!(self, func, data, subscribe, StringMsg, unsubscribe, StringMsg);
+        func_notype!(self, func, list_services, list_topics);
         
         func_end!(func)
     }
 
+    /// 订阅事件主题
+    fn subscribe(&mut self, topic: StringMsg) -> anyhow::Result<()> {
+        let topic = topic::ALL
+            .iter()
+            .copied()
+            .find(|x| *x == topic.value)
+            .ok_or_else(|| ServiceError::InvalidInput(format!("未知事件主题 {}", topic.value)))?;
+        self.subscriptions.write().unwrap().insert(topic);
+        Ok(())
+    }
+
+    /// 取消订阅事件主题
+    fn unsubscribe(&mut self, topic: StringMsg) -> anyhow::Result<()> {
+        self.subscriptions.write().unwrap().remove(topic.value.as_str());
+        Ok(())
+    }
+
+    /// 列出所有事件主题
+    fn list_topics(&self) -> anyhow::Result<VecStringMsg> {
+        Ok(VecStringMsg {
+            values: topic::ALL.iter().map(|x| x.to_string()).collect(),
+        })
+    }
+
+    /// 查找服务注册信息
+    fn find_entry(service: &str) -> anyhow::Result<&'static ServiceEntry> {
+        Ok(SERVICE_REGISTRY
+            .iter()
+            .find(|entry| entry.descriptor.name == service)
+            .ok_or_else(|| ServiceError::NotFound(format!("未知服务 {}", service)))?)
+    }
 
-    const UTILS_SERVICE: &'static str = "UtilsService";
-    const SYNC_FILE_SERVICE: &'static str = "SyncFileService";
-    const AUTO_START_SERVICE: &'static str = "AutoStartService";
-    const DISPLAY_LIGHT_SERVICE: &'static str = "DisplayLightService";
-    const DISPLAY_MODE_SERVICE: &'static str = "DisplayModeService";
-    const ABOUT_SERVICE: &'static str = "AboutService";
-    const AI_SERVICE: &'static str = "AiService";
-    const IMAGE_SPLIT_SERVICE: &'static str = "ImageSplitService";
-    const TAR_PDF_SERVICE: &'static str = "TarPdfService";
+    /// 服务是否已启用
+    fn is_enabled(&self, service: &str) -> bool {
+        self.services.contains_key(service) || self.stream_services.contains_key(service)
+    }
 
     async fn enable_service(&mut self, service: StringMsg) -> anyhow::Result<()> {
-        let service = service.value;
-        if self.services.contains_key(service.as_str()) || self.stream_services.contains_key(service.as_str()) {
-            return Err(anyhow::anyhow!("服务已初始化"));
-        }
-        
-        if service == Self::UTILS_SERVICE {
-            self.add_imm_service(Box::new(UtilsService::new(self.global_data.clone())), Self::UTILS_SERVICE);
-        }
-        
-        if service == Self::SYNC_FILE_SERVICE {
-            let service = SyncFileService::new(self.global_data.clone()).await?;
-            self.add_service(Box::new(service), Self::SYNC_FILE_SERVICE);
-        } 
-        
-        if service == Self::AUTO_START_SERVICE {
-            self.add_imm_service(Box::new(AutoStartService::new()?), Self::AUTO_START_SERVICE);
-        }
-        
-        if service == Self::ABOUT_SERVICE{
-            self.add_service(Box::new(AboutService::new()), Self::ABOUT_SERVICE);
-        }
-        
-        if service == Self::DISPLAY_LIGHT_SERVICE {
-            #[cfg(target_os = "windows")] {
-                self.add_imm_service(Box::new(DisplayLight::new()), Self::DISPLAY_LIGHT_SERVICE);
-            }
-            #[cfg(target_os = "linux")] {
-                self.add_service(Box::new(DisplayLight::new().await.ok_or(anyhow::anyhow!("创建light服务失败"))?), Self::DISPLAY_LIGHT_SERVICE);
-            }
+        let entry = Self::find_entry(&service.value)?;
+        let descriptor = entry.descriptor;
+        if self.is_enabled(descriptor.name) {
+            return Err(ServiceError::InvalidInput("服务已初始化".to_string()).into());
         }
-        
-        if service == Self::DISPLAY_MODE_SERVICE {
-            #[cfg(target_os = "windows")]
-            {
-                self.add_lazy_service(Box::new(DisplayMode::new(self.global_data.clone()).await), Self::DISPLAY_MODE_SERVICE);
-            }
-            #[cfg(target_os = "linux")]
-            {
-                self.add_service(Box::new(DisplayMode::new(self.global_data.clone()).await?), Self::DISPLAY_MODE_SERVICE);
-            }
+        if !descriptor.is_available() {
+            return Err(ServiceError::InvalidInput(format!(
+                "服务{}不支持当前平台{}",
+                descriptor.name,
+                std::env::consts::OS
+            ))
+            .into());
         }
-        
-        if service == Self::AI_SERVICE {
-            self.add_stream_service(Box::new(BaiduAiService::new(self.global_data.clone()).await), Self::AI_SERVICE);
+
+        let instance = (entry.factory)(self.global_data.clone()).await?;
+        if let Some(reader) = instance.read_handler() {
+            self.read_services.insert(descriptor.name, reader);
         }
-        if service == Self::IMAGE_SPLIT_SERVICE {
-            self.add_service(Box::new(ImageSplitService::new()), Self::IMAGE_SPLIT_SERVICE);
+        match instance {
+            ServiceInstance::Service(service) => self.add_service(service, descriptor.name),
+            ServiceInstance::LazyService(service) => self.add_lazy_service(service, descriptor.name),
+            ServiceInstance::ImmService(service) => self.add_imm_service(service, descriptor.name),
+            ServiceInstance::StreamService(service) => self.add_stream_service(service, descriptor.name),
         }
-        if service == Self::TAR_PDF_SERVICE {
-            self.add_stream_service(Box::new(TarPdfService::new(self.global_data.clone()).await), Self::TAR_PDF_SERVICE);
+        Ok(())
+    }
+
+    /// 停用服务
+    /// 取消进行中的请求并关闭服务(保存数据)，然后将其移除以释放资源
+    /// 停用后可再次启用，关闭失败时服务保持启用
+    async fn disable_service(&mut self, service: StringMsg) -> anyhow::Result<()> {
+        let name = service.value.as_str();
+        // 取消进行中的请求，释放服务锁
+        self.cancel_service(name);
+        if let Some(service) = self.services.get(name) {
+            service.close().await?;
+            self.services.remove(name);
+            self.read_services.remove(name);
+        } else if let Some(service) = self.stream_services.get(name) {
+            service.close().await?;
+            self.stream_services.remove(name);
+            self.read_services.remove(name);
+        } else {
+            return Err(ServiceError::NotFound(format!("服务{}未启用", name)).into());
         }
-        
+        info!("服务{}已停用", name);
         Ok(())
     }
 
+    /// 查询服务在当前平台是否可用
     async fn get_router_enabled(&self, service: StringMsg) -> anyhow::Result<BoolMsg> {
         info!("查询{}", service.value);
-        Ok(BoolMsg{value: true})
+        let available = SERVICE_REGISTRY
+            .iter()
+            .any(|entry| entry.descriptor.name == service.value && entry.descriptor.is_available());
+        Ok(BoolMsg { value: available })
+    }
+
+    /// 列出所有已注册服务
+    fn list_services(&self) -> anyhow::Result<ServiceListMsg> {
+        Ok(ServiceListMsg {
+            services: SERVICE_REGISTRY
+                .iter()
+                .map(|entry| self.service_info(entry.descriptor))
+                .collect(),
+        })
+    }
+
+    /// 查询服务描述
+    async fn describe_service(&self, service: StringMsg) -> anyhow::Result<ServiceInfoMsg> {
+        let entry = Self::find_entry(&service.value)?;
+        Ok(self.service_info(entry.descriptor))
+    }
+
+    fn service_info(&self, descriptor: &ServiceDescriptor) -> ServiceInfoMsg {
+        ServiceInfoMsg {
+            name: descriptor.name.to_string(),
+            available: descriptor.is_available(),
+            enabled: self.is_enabled(descriptor.name),
+            is_stream: descriptor.is_stream(),
+            platforms: descriptor.platforms.iter().map(|x| x.to_string()).collect(),
+            funcs: descriptor
+                .funcs
+                .iter()
+                .map(|f| FuncInfoMsg {
+                    name: f.name.to_string(),
+                    request: f.request.to_string(),
+                    response: f.response.to_string(),
+                    is_stream: f.is_stream,
+                    read_only: f.read_only,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// 服务实例
+enum ServiceInstance {
+    Service(Box<dyn Service>),
+    #[allow(dead_code)]
+    LazyService(Box<dyn LazyService>),
+    ImmService(Box<dyn ImmService>),
+    StreamService(Box<dyn StreamService>),
+}
+
+impl ServiceInstance {
+    /// 服务的只读处理器
+    fn read_handler(&self) -> Option<Arc<Box<dyn ImmService>>> {
+        match self {
+            ServiceInstance::Service(service) => service.read_handler(),
+            ServiceInstance::LazyService(service) => service.read_handler(),
+            ServiceInstance::ImmService(_) => None,
+            ServiceInstance::StreamService(service) => service.read_handler(),
+        }
     }
-}
\ No newline at end of file
+}
+
+/// 服务注册信息
+struct ServiceEntry {
+    // 服务描述
+    descriptor: &'static ServiceDescriptor,
+    // 服务构造
+    factory: fn(GlobalData) -> BoxFuture<'static, anyhow::Result<ServiceInstance>>,
+}
+
+/// 服务注册表
+/// 新增服务时在此注册
+const SERVICE_REGISTRY: &[ServiceEntry] = &[
+    ServiceEntry {
+        descriptor: &service::utils::DESCRIPTOR,
+        factory: |gd| async move { Ok(ServiceInstance::ImmService(Box::new(UtilsService::new(gd)))) }.boxed(),
+    },
+    ServiceEntry {
+        descriptor: &service::syncfile::DESCRIPTOR,
+        factory: |gd| {
+            async move { Ok(ServiceInstance::Service(Box::new(SyncFileService::new(gd).await?))) }.boxed()
+        },
+    },
+    ServiceEntry {
+        descriptor: &service::settings::autostart::DESCRIPTOR,
+        factory: |_| async move { Ok(ServiceInstance::ImmService(Box::new(AutoStartService::new()?))) }.boxed(),
+    },
+    ServiceEntry {
+        descriptor: &service::settings::about::DESCRIPTOR,
+        factory: |_| async move { Ok(ServiceInstance::Service(Box::new(AboutService::new()))) }.boxed(),
+    },
+    ServiceEntry {
+        descriptor: &service::display::LIGHT_DESCRIPTOR,
+        factory: |_| {
+            async move {
+                #[cfg(target_os = "windows")]
+                let service = ServiceInstance::ImmService(Box::new(DisplayLight::new()));
+                #[cfg(target_os = "linux")]
+                let service = ServiceInstance::Service(Box::new(
+                    DisplayLight::new()
+                        .await
+                        .ok_or(anyhow::anyhow!("创建light服务失败"))?,
+                ));
+                Ok(service)
+            }
+            .boxed()
+        },
+    },
+    ServiceEntry {
+        descriptor: &service::display::MODE_DESCRIPTOR,
+        factory: |gd| {
+            async move {
+                #[cfg(target_os = "windows")]
+                let service = ServiceInstance::LazyService(Box::new(DisplayMode::new(gd).await));
+                #[cfg(target_os = "linux")]
+                let service = ServiceInstance::Service(Box::new(DisplayMode::new(gd).await?));
+                Ok(service)
+            }
+            .boxed()
+        },
+    },
+    ServiceEntry {
+        descriptor: &service::ai::DESCRIPTOR,
+        factory: |gd| {
+            async move { Ok(ServiceInstance::StreamService(Box::new(BaiduAiService::new(gd).await))) }.boxed()
+        },
+    },
+    ServiceEntry {
+        descriptor: &service::img::img_split::DESCRIPTOR,
+        factory: |_| async move { Ok(ServiceInstance::Service(Box::new(ImageSplitService::new()))) }.boxed(),
+    },
+    ServiceEntry {
+        descriptor: &service::pdf::tar_pdf::DESCRIPTOR,
+        factory: |gd| {
+            async move { Ok(ServiceInstance::StreamService(Box::new(TarPdfService::new(gd).await))) }.boxed()
+        },
+    },
+];
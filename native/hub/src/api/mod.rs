@@ -1,5 +1,6 @@
 pub mod api;
 
+use crate::messages::common::ErrorMsg;
 use rinf::{DartSignalBinary, RustSignalBinary};
 use serde::{Deserialize, Serialize};
 
@@ -15,6 +16,10 @@ pub struct BaseRequest {
     pub func: String,
     // 是否为流式请求
     pub is_stream: bool,
+    // 是否为取消请求，为true时取消id对应的进行中请求
+    pub is_cancel: bool,
+    // 超时时间(毫秒)，为空时使用服务默认超时，为0时不限制
+    pub timeout: Option<u32>,
 }
 
 // 基础响应
@@ -29,4 +34,17 @@ pub struct BaseResponse {
     pub is_stream: bool,
     // 流式响应是否结束
     pub is_end: bool,
-}
\ No newline at end of file
+    // 请求是否已被取消
+    pub is_cancel: bool,
+    // 请求是否已超时
+    pub is_timeout: bool,
+    // 结构化错误信息，存在时msg为其面向用户的错误信息
+    pub error: Option<ErrorMsg>,
+}
+// 基础事件
+// 服务主动推送的事件，事件数据位于binary字段中
+#[derive(Debug, Serialize, RustSignalBinary)]
+pub struct BaseEvent {
+    // 事件主题
+    pub topic: String,
+}
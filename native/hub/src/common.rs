@@ -1,6 +1,9 @@
 use std::error::Error;
 
+pub mod error;
+pub mod event_bus;
 pub mod global_data;
+pub mod secret;
 pub mod utils;
 
 /// Using this `Result` type alias allows
@@ -0,0 +1,108 @@
+use crate::messages::common::{ErrorCategoryMsg, ErrorMsg};
+use std::fmt::{Display, Formatter};
+
+/// 服务错误
+/// 服务在handle中返回该错误，由分发层转换为带错误码的响应
+#[derive(Debug, Clone)]
+pub enum ServiceError {
+    /// 未登录或账户信息缺失
+    NotLoggedIn(String),
+    /// 网络错误
+    Network(String),
+    /// 配置错误或缺失
+    Config(String),
+    /// 请求参数错误
+    InvalidInput(String),
+    /// 资源不存在
+    NotFound(String),
+    /// 远端服务返回错误
+    Remote(String),
+    /// 资源被其它设备锁定
+    Locked(String),
+    /// 请求已取消
+    Cancelled,
+    /// 请求超时
+    Timeout(String),
+    /// 内部错误
+    Internal(String),
+}
+
+impl ServiceError {
+    /// 稳定的错误码 已发布的错误码不允许修改
+    pub fn code(&self) -> u32 {
+        match self {
+            ServiceError::NotLoggedIn(_) => 1001,
+            ServiceError::Network(_) => 2001,
+            ServiceError::Config(_) => 3001,
+            ServiceError::InvalidInput(_) => 4001,
+            ServiceError::NotFound(_) => 4004,
+            ServiceError::Remote(_) => 5001,
+            ServiceError::Locked(_) => 5002,
+            ServiceError::Cancelled => 6001,
+            ServiceError::Timeout(_) => 6002,
+            ServiceError::Internal(_) => 9001,
+        }
+    }
+
+    /// 错误类别
+    pub fn category(&self) -> ErrorCategoryMsg {
+        match self {
+            ServiceError::NotLoggedIn(_) => ErrorCategoryMsg::Auth,
+            ServiceError::Network(_) => ErrorCategoryMsg::Network,
+            ServiceError::Config(_) => ErrorCategoryMsg::Config,
+            ServiceError::InvalidInput(_) | ServiceError::NotFound(_) => ErrorCategoryMsg::Input,
+            ServiceError::Remote(_) | ServiceError::Locked(_) => ErrorCategoryMsg::Remote,
+            ServiceError::Cancelled => ErrorCategoryMsg::Cancelled,
+            ServiceError::Timeout(_) => ErrorCategoryMsg::Timeout,
+            ServiceError::Internal(_) => ErrorCategoryMsg::Internal,
+        }
+    }
+
+    /// 面向用户的错误信息
+    pub fn message(&self) -> &str {
+        match self {
+            ServiceError::NotLoggedIn(msg)
+            | ServiceError::Network(msg)
+            | ServiceError::Config(msg)
+            | ServiceError::InvalidInput(msg)
+            | ServiceError::NotFound(msg)
+            | ServiceError::Remote(msg)
+            | ServiceError::Locked(msg)
+            | ServiceError::Timeout(msg)
+            | ServiceError::Internal(msg) => msg,
+            ServiceError::Cancelled => "请求已取消",
+        }
+    }
+
+    /// 转换为响应消息
+    pub fn to_msg(&self, detail: Option<String>) -> ErrorMsg {
+        ErrorMsg {
+            code: self.code(),
+            category: self.category(),
+            message: self.message().to_string(),
+            detail,
+        }
+    }
+
+    /// 从任意错误中识别服务错误
+    /// 未标记的网络错误归为Network，其余归为Internal
+    pub fn from_anyhow(e: &anyhow::Error) -> Self {
+        if let Some(e) = e.downcast_ref::<ServiceError>() {
+            return e.clone();
+        }
+        if e.downcast_ref::<reqwest::Error>().is_some()
+            || e.downcast_ref::<reqwest_dav::Error>().is_some()
+        {
+            return ServiceError::Network(e.to_string());
+        }
+        ServiceError::Internal(e.to_string())
+    }
+}
+
+impl Display for ServiceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message())
+    }
+}
+
+impl std::error::Error for ServiceError {}
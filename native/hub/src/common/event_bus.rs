@@ -0,0 +1,59 @@
+use log::error;
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+/// 事件主题
+pub mod topic {
+    /// 系统主题色变化
+    pub const COLOR_CHANGED: &str = "colorChanged";
+    /// 壁纸变化
+    pub const WALLPAPER_CHANGED: &str = "wallpaperChanged";
+    /// 显示器插拔
+    pub const MONITOR_CHANGED: &str = "monitorChanged";
+    /// 文件夹同步完成
+    pub const SYNC_FINISHED: &str = "syncFinished";
+
+    /// 所有主题
+    pub const ALL: &[&str] = &[COLOR_CHANGED, WALLPAPER_CHANGED, MONITOR_CHANGED, SYNC_FINISHED];
+}
+
+// 事件缓冲数量 订阅方处理不及时会丢弃旧事件
+const EVENT_CAPACITY: usize = 64;
+
+/// 事件
+#[derive(Debug, Clone)]
+pub struct Event {
+    // 主题
+    pub topic: &'static str,
+    // 序列化后的事件数据
+    pub data: Vec<u8>,
+}
+
+/// 事件总线
+/// 服务发布事件，由ApiService按前端订阅的主题推送
+#[derive(Clone)]
+pub struct EventBus {
+    tx: broadcast::Sender<Event>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(EVENT_CAPACITY);
+        Self { tx }
+    }
+
+    /// 发布事件 无订阅者时忽略
+    pub fn publish<T: Serialize>(&self, topic: &'static str, msg: &T) {
+        match rinf::serialize(msg) {
+            Ok(data) => {
+                let _ = self.tx.send(Event { topic, data });
+            }
+            Err(e) => error!("事件{}序列化失败: {}", topic, e),
+        }
+    }
+
+    /// 订阅全部事件
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.tx.subscribe()
+    }
+}
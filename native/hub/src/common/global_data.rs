@@ -1,5 +1,9 @@
+use crate::common::error::ServiceError;
+use crate::common::event_bus::EventBus;
+use crate::common::secret::{SecretStore, SECRET_REF_PREFIX};
 use crate::common::utils::get_config_dir;
 use anyhow::Result;
+use log::warn;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::path::PathBuf;
@@ -9,11 +13,20 @@ use tokio_rusqlite::{params, Connection};
 #[derive(Clone)]
 pub struct GlobalData {
     conn: Connection,
+    // 事件总线
+    event_bus: EventBus,
+    // 系统密钥存储
+    secrets: SecretStore,
 }
 
 impl GlobalData {
     pub async fn new() -> Result<Self> {
-        let conn = Connection::open(config_dir()?).await?;
+        Self::open(config_dir()?).await
+    }
+
+    /// 打开指定路径的数据库
+    pub(crate) async fn open(path: PathBuf) -> Result<Self> {
+        let conn = Connection::open(path).await?;
         conn.call(|conn| {
             conn.execute(
                 r#"
@@ -28,7 +41,16 @@ CREATE TABLE IF NOT EXISTS KV (
         })
         .await?;
 
-        Ok(Self { conn })
+        Ok(Self {
+            conn,
+            event_bus: EventBus::new(),
+            secrets: SecretStore::default(),
+        })
+    }
+
+    /// 事件总线
+    pub fn event_bus(&self) -> &EventBus {
+        &self.event_bus
     }
 
     pub async fn set_data<T>(&self, key: String, value: &T) -> Result<()>
@@ -53,6 +75,76 @@ CREATE TABLE IF NOT EXISTS KV (
         }
     }
 
+    pub async fn del_data(&self, key: String) -> Result<()> {
+        self.delete(key).await
+    }
+
+    /// 保存密钥 存入系统密钥服务，表中只记录引用
+    /// linux下密钥服务不可用时返回错误；其它平台暂不支持密钥服务，明文保存
+    pub async fn set_secret(&self, key: String, value: &str) -> Result<()> {
+        if self.store_secret(key.clone(), value).await? {
+            return Ok(());
+        }
+        if cfg!(target_os = "linux") {
+            return Err(ServiceError::Config(
+                "系统密钥服务不可用，无法安全保存密钥，请启用gnome-keyring或KWallet后重试".to_string(),
+            )
+            .into());
+        }
+        self.set_data(key, &value).await
+    }
+
+    /// 读取密钥 旧版本明文保存在其它数据中的密钥迁移到系统密钥服务，迁移成功后清空
+    /// 迁移失败时保留原数据，密钥只在内存中使用，不另存明文
+    pub async fn migrate_secret(&self, key: String, legacy: &mut Option<String>) -> Option<String> {
+        let Some(value) = legacy.clone() else {
+            return self.get_secret(key).await;
+        };
+        match self.store_secret(key.clone(), &value).await {
+            Ok(true) => *legacy = None,
+            Ok(false) => {}
+            Err(e) => warn!("迁移{}到系统密钥服务失败，保留原数据: {}", key, e),
+        }
+        Some(value)
+    }
+
+    /// 读取密钥 明文保存的旧数据读取后迁移到系统密钥服务
+    /// 迁移失败时不修改原数据，密钥只在内存中使用
+    pub async fn get_secret(&self, key: String) -> Option<String> {
+        let value: String = self.get_data(key.clone()).await?;
+        if let Some(name) = value.strip_prefix(SECRET_REF_PREFIX) {
+            return match self.secrets.load(name).await {
+                Ok(v) => v,
+                Err(e) => {
+                    warn!("从系统密钥服务读取{}失败: {}", name, e);
+                    None
+                }
+            };
+        }
+        if let Err(e) = self.store_secret(key.clone(), &value).await {
+            warn!("迁移{}到系统密钥服务失败，保留原数据: {}", key, e);
+        }
+        Some(value)
+    }
+
+    /// 删除密钥
+    pub async fn del_secret(&self, key: String) -> Result<()> {
+        if let Err(e) = self.secrets.delete(&key).await {
+            warn!("从系统密钥服务删除{}失败: {}", key, e);
+        }
+        self.delete(key).await
+    }
+
+    /// 存入系统密钥服务并在表中记录引用 密钥服务不可用时返回false，不修改表中数据
+    async fn store_secret(&self, key: String, value: &str) -> Result<bool> {
+        if !self.secrets.store(&key, value).await? {
+            return Ok(false);
+        }
+        let reference = format!("{}{}", SECRET_REF_PREFIX, key);
+        self.set_data(key, &reference).await?;
+        Ok(true)
+    }
+
     async fn store(&self, key: String, value: String) -> Result<()> {
         self.conn
             .call(move |conn| {
@@ -79,6 +171,17 @@ CREATE TABLE IF NOT EXISTS KV (
             .await?;
         Ok(result)
     }
+
+    async fn delete(&self, key: String) -> Result<()> {
+        self.conn
+            .call(move |conn| {
+                let mut stmt = conn.prepare_cached("DELETE FROM KV WHERE id = ?1")?;
+                stmt.execute(params![key])?;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
 }
 
 fn config_dir() -> Result<PathBuf> {
@@ -0,0 +1,208 @@
+use anyhow::Result;
+
+/// KV表中密钥引用的前缀 实际内容保存在系统密钥服务中
+pub const SECRET_REF_PREFIX: &str = "secret-service:";
+
+/// 系统密钥存储
+/// linux下通过freedesktop Secret Service保存，其它平台不支持
+#[derive(Clone, Default)]
+pub struct SecretStore {
+    #[cfg(target_os = "linux")]
+    inner: std::sync::Arc<tokio::sync::OnceCell<Option<linux::SecretService>>>,
+}
+
+impl SecretStore {
+    /// 保存密钥 不支持或密钥服务不可用时返回false
+    pub async fn store(&self, key: &str, value: &str) -> Result<bool> {
+        #[cfg(target_os = "linux")]
+        if let Some(service) = self.service().await {
+            service.store(key, value).await?;
+            return Ok(true);
+        }
+        let _ = (key, value);
+        Ok(false)
+    }
+
+    /// 读取密钥
+    pub async fn load(&self, key: &str) -> Result<Option<String>> {
+        #[cfg(target_os = "linux")]
+        if let Some(service) = self.service().await {
+            return service.load(key).await;
+        }
+        let _ = key;
+        Ok(None)
+    }
+
+    /// 删除密钥
+    pub async fn delete(&self, key: &str) -> Result<()> {
+        #[cfg(target_os = "linux")]
+        if let Some(service) = self.service().await {
+            return service.delete(key).await;
+        }
+        let _ = key;
+        Ok(())
+    }
+
+    /// 连接密钥服务 不可用时为空
+    #[cfg(target_os = "linux")]
+    async fn service(&self) -> Option<&linux::SecretService> {
+        self.inner
+            .get_or_init(|| async {
+                match linux::SecretService::connect().await {
+                    Ok(x) => Some(x),
+                    Err(e) => {
+                        log::warn!("系统密钥服务不可用: {}", e);
+                        None
+                    }
+                }
+            })
+            .await
+            .as_ref()
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use crate::common::error::ServiceError;
+    use crate::common::APP_NAME;
+    use crate::dbus::secret::{
+        OrgFreedesktopSecretCollection, OrgFreedesktopSecretItem, OrgFreedesktopSecretPrompt,
+        OrgFreedesktopSecretPromptCompleted, OrgFreedesktopSecretService,
+    };
+    use anyhow::Result;
+    use dbus::arg::{PropMap, RefArg, Variant};
+    use dbus::message::SignalArgs;
+    use dbus::nonblock::{Proxy, SyncConnection};
+    use dbus::Path;
+    use dbus_tokio::connection;
+    use futures::StreamExt;
+    use log::error;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    const BUS_NAME: &str = "org.freedesktop.secrets";
+    const SERVICE_PATH: &str = "/org/freedesktop/secrets";
+    // 默认密钥环
+    const DEFAULT_COLLECTION: &str = "/org/freedesktop/secrets/aliases/default";
+    const TIMEOUT: Duration = Duration::from_secs(5);
+    // 等待用户解锁密钥环的时间
+    const PROMPT_TIMEOUT: Duration = Duration::from_secs(120);
+
+    /// Secret Service连接
+    pub(super) struct SecretService {
+        conn: Arc<SyncConnection>,
+        // 明文传输会话 连接为本机会话总线
+        session: Path<'static>,
+    }
+
+    impl SecretService {
+        pub(super) async fn connect() -> Result<Self> {
+            let (resource, conn) = connection::new_session_sync()?;
+            tokio::spawn(async {
+                let err = resource.await;
+                error!("Lost connection to D-Bus: {}", err);
+            });
+            let session = Proxy::new(BUS_NAME, SERVICE_PATH, TIMEOUT, conn.clone())
+                .open_session("plain", Variant(""))
+                .await?
+                .result;
+            Ok(Self { conn, session })
+        }
+
+        fn proxy<'a>(&self, path: impl Into<Path<'a>>) -> Proxy<'a, Arc<SyncConnection>> {
+            Proxy::new(BUS_NAME, path, TIMEOUT, self.conn.clone())
+        }
+
+        /// 保存密钥 已存在时替换
+        pub(super) async fn store(&self, key: &str, value: &str) -> Result<()> {
+            self.unlock(vec![Path::from(DEFAULT_COLLECTION)]).await?;
+            let secret = (
+                self.session.clone(),
+                Vec::new(),
+                value.as_bytes().to_vec(),
+                "text/plain".to_string(),
+            );
+            let (_, prompt) = self
+                .proxy(DEFAULT_COLLECTION)
+                .create_item(item_properties(key), secret, true)
+                .await?;
+            self.prompt(prompt).await
+        }
+
+        /// 读取密钥
+        pub(super) async fn load(&self, key: &str) -> Result<Option<String>> {
+            let Some(item) = self.search(key).await?.into_iter().next() else {
+                return Ok(None);
+            };
+            let (_, _, value, _) = self.proxy(item).get_secret(self.session.clone()).await?;
+            Ok(Some(String::from_utf8(value)?))
+        }
+
+        /// 删除密钥
+        pub(super) async fn delete(&self, key: &str) -> Result<()> {
+            for item in self.search(key).await? {
+                let prompt = self.proxy(item).delete().await?;
+                self.prompt(prompt).await?;
+            }
+            Ok(())
+        }
+
+        /// 按属性查找密钥 已锁定的先解锁
+        async fn search(&self, key: &str) -> Result<Vec<Path<'static>>> {
+            let (mut unlocked, locked) = self
+                .proxy(SERVICE_PATH)
+                .search_items(HashMap::from([("application", APP_NAME), ("key", key)]))
+                .await?;
+            if !locked.is_empty() {
+                self.unlock(locked.clone()).await?;
+                unlocked.extend(locked);
+            }
+            Ok(unlocked)
+        }
+
+        /// 解锁 需要时等待用户输入密码
+        async fn unlock(&self, objects: Vec<Path<'static>>) -> Result<()> {
+            let (_, prompt) = self.proxy(SERVICE_PATH).unlock(objects).await?;
+            self.prompt(prompt).await
+        }
+
+        /// 显示提示并等待完成 路径为/时无需提示
+        async fn prompt(&self, prompt: Path<'static>) -> Result<()> {
+            if &*prompt == "/" {
+                return Ok(());
+            }
+            let rule = OrgFreedesktopSecretPromptCompleted::match_rule(None, Some(&prompt)).static_clone();
+            let (signal, mut stream) = self
+                .conn
+                .add_match(rule)
+                .await?
+                .stream::<OrgFreedesktopSecretPromptCompleted>();
+            self.proxy(prompt).prompt("").await?;
+            let completed = tokio::time::timeout(PROMPT_TIMEOUT, stream.next()).await;
+            self.conn.remove_match(signal.token()).await?;
+            match completed {
+                Ok(Some((_, x))) if !x.dismissed => Ok(()),
+                _ => Err(ServiceError::InvalidInput("已取消解锁系统密钥环".to_string()).into()),
+            }
+        }
+    }
+
+    /// 密钥项属性 以应用名+key区分
+    fn item_properties(key: &str) -> PropMap {
+        let attributes: HashMap<String, String> = HashMap::from([
+            ("application".to_string(), APP_NAME.to_string()),
+            ("key".to_string(), key.to_string()),
+        ]);
+        PropMap::from([
+            (
+                "org.freedesktop.Secret.Item.Label".to_string(),
+                Variant(Box::new(format!("{} {}", APP_NAME, key)) as Box<dyn RefArg>),
+            ),
+            (
+                "org.freedesktop.Secret.Item.Attributes".to_string(),
+                Variant(Box::new(attributes) as Box<dyn RefArg>),
+            ),
+        ])
+    }
+}
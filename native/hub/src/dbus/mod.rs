@@ -3,4 +3,8 @@ pub mod wallpaper;
 #[cfg(target_os = "linux")]
 pub mod power_manager;
 #[cfg(target_os = "linux")]
-pub mod screensaver;
\ No newline at end of file
+pub mod screensaver;
+#[cfg(target_os = "linux")]
+pub mod secret;
+#[cfg(target_os = "linux")]
+pub mod network_manager;
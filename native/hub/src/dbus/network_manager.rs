@@ -0,0 +1,16 @@
+// NetworkManager 接口 仅包含用到的属性，见 https://networkmanager.dev/docs/api/latest/gdbus-org.freedesktop.NetworkManager.html
+use dbus as dbus;
+#[allow(unused_imports)]
+use dbus::arg;
+use dbus::nonblock;
+
+pub trait OrgFreedesktopNetworkManager {
+    fn metered(&self) -> nonblock::MethodReply<u32>;
+}
+
+impl<'a, T: nonblock::NonblockReply, C: ::std::ops::Deref<Target=T>> OrgFreedesktopNetworkManager for nonblock::Proxy<'a, C> {
+
+    fn metered(&self) -> nonblock::MethodReply<u32> {
+        <Self as nonblock::stdintf::org_freedesktop_dbus::Properties>::get(self, "org.freedesktop.NetworkManager", "Metered")
+    }
+}
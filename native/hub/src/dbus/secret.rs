@@ -0,0 +1,109 @@
+// freedesktop Secret Service 接口 仅包含用到的方法，见 https://specifications.freedesktop.org/secret-service/
+use dbus as dbus;
+#[allow(unused_imports)]
+use dbus::arg;
+use dbus::nonblock;
+
+/// 密钥内容 (会话，参数，值，类型)
+pub type Secret = (dbus::Path<'static>, Vec<u8>, Vec<u8>, String);
+
+pub trait OrgFreedesktopSecretService {
+    fn open_session(&self, algorithm: &str, input: arg::Variant<&str>) -> nonblock::MethodReply<OrgFreedesktopSecretServiceOpenSession>;
+    fn search_items(&self, attributes: ::std::collections::HashMap<&str, &str>) -> nonblock::MethodReply<(Vec<dbus::Path<'static>>, Vec<dbus::Path<'static>>)>;
+    fn unlock(&self, objects: Vec<dbus::Path<'static>>) -> nonblock::MethodReply<(Vec<dbus::Path<'static>>, dbus::Path<'static>)>;
+}
+
+/// OpenSession的返回值 明文传输时输出为空，只读取会话路径
+#[derive(Debug)]
+pub struct OrgFreedesktopSecretServiceOpenSession {
+    pub result: dbus::Path<'static>,
+}
+
+impl arg::ReadAll for OrgFreedesktopSecretServiceOpenSession {
+    fn read(i: &mut arg::Iter) -> Result<Self, arg::TypeMismatchError> {
+        i.next();
+        Ok(OrgFreedesktopSecretServiceOpenSession {
+            result: i.read()?,
+        })
+    }
+}
+
+impl<'a, T: nonblock::NonblockReply, C: ::std::ops::Deref<Target=T>> OrgFreedesktopSecretService for nonblock::Proxy<'a, C> {
+
+    fn open_session(&self, algorithm: &str, input: arg::Variant<&str>) -> nonblock::MethodReply<OrgFreedesktopSecretServiceOpenSession> {
+        self.method_call("org.freedesktop.Secret.Service", "OpenSession", (algorithm, input, ))
+    }
+
+    fn search_items(&self, attributes: ::std::collections::HashMap<&str, &str>) -> nonblock::MethodReply<(Vec<dbus::Path<'static>>, Vec<dbus::Path<'static>>)> {
+        self.method_call("org.freedesktop.Secret.Service", "SearchItems", (attributes, ))
+    }
+
+    fn unlock(&self, objects: Vec<dbus::Path<'static>>) -> nonblock::MethodReply<(Vec<dbus::Path<'static>>, dbus::Path<'static>)> {
+        self.method_call("org.freedesktop.Secret.Service", "Unlock", (objects, ))
+    }
+}
+
+pub trait OrgFreedesktopSecretCollection {
+    fn create_item(&self, properties: arg::PropMap, secret: Secret, replace: bool) -> nonblock::MethodReply<(dbus::Path<'static>, dbus::Path<'static>)>;
+}
+
+impl<'a, T: nonblock::NonblockReply, C: ::std::ops::Deref<Target=T>> OrgFreedesktopSecretCollection for nonblock::Proxy<'a, C> {
+
+    fn create_item(&self, properties: arg::PropMap, secret: Secret, replace: bool) -> nonblock::MethodReply<(dbus::Path<'static>, dbus::Path<'static>)> {
+        self.method_call("org.freedesktop.Secret.Collection", "CreateItem", (properties, secret, replace, ))
+    }
+}
+
+pub trait OrgFreedesktopSecretItem {
+    fn get_secret(&self, session: dbus::Path<'static>) -> nonblock::MethodReply<Secret>;
+    fn delete(&self) -> nonblock::MethodReply<dbus::Path<'static>>;
+}
+
+impl<'a, T: nonblock::NonblockReply, C: ::std::ops::Deref<Target=T>> OrgFreedesktopSecretItem for nonblock::Proxy<'a, C> {
+
+    fn get_secret(&self, session: dbus::Path<'static>) -> nonblock::MethodReply<Secret> {
+        self.method_call("org.freedesktop.Secret.Item", "GetSecret", (session, ))
+            .and_then(|r: (Secret, )| Ok(r.0, ))
+    }
+
+    fn delete(&self) -> nonblock::MethodReply<dbus::Path<'static>> {
+        self.method_call("org.freedesktop.Secret.Item", "Delete", ())
+            .and_then(|r: (dbus::Path<'static>, )| Ok(r.0, ))
+    }
+}
+
+pub trait OrgFreedesktopSecretPrompt {
+    fn prompt(&self, window_id: &str) -> nonblock::MethodReply<()>;
+}
+
+/// 提示完成 只读取是否被取消
+#[derive(Debug)]
+pub struct OrgFreedesktopSecretPromptCompleted {
+    pub dismissed: bool,
+}
+
+impl arg::ReadAll for OrgFreedesktopSecretPromptCompleted {
+    fn read(i: &mut arg::Iter) -> Result<Self, arg::TypeMismatchError> {
+        Ok(OrgFreedesktopSecretPromptCompleted {
+            dismissed: i.read()?,
+        })
+    }
+}
+
+impl arg::AppendAll for OrgFreedesktopSecretPromptCompleted {
+    fn append(&self, i: &mut arg::IterAppend) {
+        arg::RefArg::append(&self.dismissed, i);
+    }
+}
+
+impl dbus::message::SignalArgs for OrgFreedesktopSecretPromptCompleted {
+    const NAME: &'static str = "Completed";
+    const INTERFACE: &'static str = "org.freedesktop.Secret.Prompt";
+}
+
+impl<'a, T: nonblock::NonblockReply, C: ::std::ops::Deref<Target=T>> OrgFreedesktopSecretPrompt for nonblock::Proxy<'a, C> {
+
+    fn prompt(&self, window_id: &str) -> nonblock::MethodReply<()> {
+        self.method_call("org.freedesktop.Secret.Prompt", "Prompt", (window_id, ))
+    }
+}
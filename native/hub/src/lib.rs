@@ -79,6 +79,11 @@ async fn base_request() -> Result<()> {
             signal.message.service, signal.message.func,
         );
         let signal = signal;
+        // 取消请求处理
+        if signal.message.is_cancel {
+            api.cancel(signal.message.id);
+            continue;
+        }
         // Api 服务特殊处理
         if signal.message.service == "ApiService" {
             // close处理
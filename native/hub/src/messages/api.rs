@@ -0,0 +1,40 @@
+use rinf::SignalPiece;
+use serde::{Deserialize, Serialize};
+
+// 服务列表
+#[derive(Debug, Serialize, Deserialize, SignalPiece)]
+pub struct ServiceListMsg {
+    pub services: Vec<ServiceInfoMsg>,
+}
+
+// 服务描述
+#[derive(Debug, Serialize, Deserialize, SignalPiece)]
+pub struct ServiceInfoMsg {
+    // 服务名称
+    pub name: String,
+    // 当前平台是否可用
+    pub available: bool,
+    // 是否已启用
+    pub enabled: bool,
+    // 是否支持流式请求
+    pub is_stream: bool,
+    // 支持的平台，为空时表示全平台
+    pub platforms: Vec<String>,
+    // 支持的方法
+    pub funcs: Vec<FuncInfoMsg>,
+}
+
+// 方法描述
+#[derive(Debug, Serialize, Deserialize, SignalPiece)]
+pub struct FuncInfoMsg {
+    // 方法名称
+    pub name: String,
+    // 请求消息类型
+    pub request: String,
+    // 响应消息类型
+    pub response: String,
+    // 是否为流式方法
+    pub is_stream: bool,
+    // 是否为只读方法，只读方法可与其他请求并行执行
+    pub read_only: bool,
+}
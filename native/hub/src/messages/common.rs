@@ -37,3 +37,46 @@ pub struct DoubleMsg  {
 pub struct DataMsg  {
     pub value: Vec<u8>,
 }
+
+// 错误信息
+#[derive(Debug, Serialize, Deserialize, SignalPiece)]
+pub struct ErrorMsg {
+    // 稳定的错误码
+    pub code: u32,
+    // 错误类别
+    pub category: ErrorCategoryMsg,
+    // 面向用户的错误信息
+    pub message: String,
+    // 错误详情
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, SignalPiece)]
+pub enum ErrorCategoryMsg {
+    // 未登录或认证失败
+    Auth = 0,
+    // 网络错误
+    Network = 1,
+    // 配置错误
+    Config = 2,
+    // 请求参数错误
+    Input = 3,
+    // 远端服务错误
+    Remote = 4,
+    // 请求已取消
+    Cancelled = 5,
+    // 请求超时
+    Timeout = 6,
+    // 内部错误
+    Internal = 7,
+}
+
+#[derive(Debug, Serialize, Deserialize, SignalPiece)]
+pub struct ProgressMsg {
+    // 当前进度
+    pub current: u32,
+    // 总数
+    pub total: u32,
+    // 当前处理项说明
+    pub label: String,
+}
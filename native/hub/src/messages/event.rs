@@ -0,0 +1,28 @@
+use rinf::SignalPiece;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, SignalPiece)]
+pub struct ColorChangedMsg {
+    // 变化后的颜色
+    pub color: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, SignalPiece)]
+pub struct WallpaperChangedMsg {
+    // 屏幕序号
+    pub screen: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, SignalPiece)]
+pub struct MonitorChangedMsg {
+    // 当前已连接的显示器
+    pub monitors: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, SignalPiece)]
+pub struct SyncFinishedMsg {
+    // 远端目录
+    pub remote_dir: String,
+    // 失败原因，为空时表示同步成功
+    pub error: Option<String>,
+}
@@ -1,7 +1,9 @@
+pub mod api;
 pub mod common;
 pub mod ai;
 pub mod syncfile;
 pub mod utils;
 pub mod display;
 pub mod image_split;
-pub mod tar_pdf;
\ No newline at end of file
+pub mod tar_pdf;
+pub mod event;
\ No newline at end of file
@@ -4,11 +4,29 @@ use serde::{Deserialize, Serialize};
 // 配置webdav
 #[derive(Debug, Serialize, Deserialize, SignalPiece)]
 pub struct WebDavConfigMsg {
+    // webdav地址 以file://开头时同步到本地文件夹(如挂载的NAS)
     pub url: String,
     pub account: String,
     pub passwd: String,
 }
 
+// 命名的同步账户
+#[derive(Debug, Clone, Serialize, Deserialize, SignalPiece)]
+pub struct SyncAccountMsg {
+    // 账户名称 唯一
+    pub name: String,
+    // 地址 以file://开头时同步到本地文件夹
+    pub url: String,
+    pub account: String,
+    pub passwd: String,
+}
+
+// 同步账户列表
+#[derive(Debug, Serialize, Deserialize, SignalPiece)]
+pub struct SyncAccountsMsg {
+    pub accounts: Vec<SyncAccountMsg>,
+}
+
 // 文件列表响应
 #[derive(Debug, Serialize, Deserialize, SignalPiece)]
 pub struct ListFileMsg {
@@ -29,6 +47,12 @@ pub struct FileMsg {
     pub del: u32,
     // 需要修改的文件数量
     pub modify: u32,
+    // 冲突的文件数量
+    pub conflict: u32,
+    // 远端是否加密 关联本地路径时需要密码
+    pub encrypted: bool,
+    // 所属账户名称
+    pub account: String,
     // 标签
     pub tag: String,
 }
@@ -44,6 +68,10 @@ pub struct SyncFileDetailMsg {
     pub del_files: Vec<String>,
     // 同步的文件
     pub modify_files: Vec<String>,
+    // 冲突的文件 已按冲突策略处理
+    pub conflict_files: Vec<String>,
+    // 被忽略的文件数量
+    pub ignored: u32,
 }
 
 #[derive(Debug, Serialize, Deserialize, SignalPiece)]
@@ -54,6 +82,155 @@ pub enum FileStatusEnumMsg {
     DOWNLOAD = 1,
     // 已同步成功
     SYNCED = 2,
+    // 需要上传及下载
+    MIXED = 3,
+    // 存在冲突
+    CONFLICT = 4,
+}
+
+// 同步计划预览
+#[derive(Debug, Serialize, Deserialize, SignalPiece)]
+pub struct SyncPreviewMsg {
+    pub remote_dir: String,
+    // 每个文件的操作
+    pub items: Vec<SyncPreviewItemMsg>,
+    // 被忽略的文件数量
+    pub ignored: u32,
+}
+
+// 单个文件的同步操作
+#[derive(Debug, Serialize, Deserialize, SignalPiece)]
+pub struct SyncPreviewItemMsg {
+    // 文件路径 文件夹以/结尾
+    pub file: String,
+    pub action: SyncActionEnumMsg,
+    // 本地文件大小 不存在时为0
+    pub local_size: u64,
+    // 远端文件大小 不存在或未知时为0
+    pub remote_size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, SignalPiece)]
+#[allow(non_camel_case_types)]
+pub enum SyncActionEnumMsg {
+    // 上传到远端
+    UPLOAD = 0,
+    // 下载到本地
+    DOWNLOAD = 1,
+    // 删除本地
+    DELETE_LOCAL = 2,
+    // 删除远端
+    DELETE_REMOTE = 3,
+    // 冲突 按冲突策略处理
+    CONFLICT = 4,
+}
+
+// 按预览结果同步文件夹
+#[derive(Debug, Serialize, Deserialize, SignalPiece)]
+pub struct ApplySyncDirMsg {
+    pub remote_dir: String,
+    // 不执行的文件 即预览中的file
+    pub exclude: Vec<String>,
+}
+
+// 冲突处理策略
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, SignalPiece)]
+pub enum ConflictPolicyMsg {
+    // 保留双方 本地文件重命名为冲突副本
+    KeepBoth = 0,
+    // 以本地为准
+    PreferLocal = 1,
+    // 以远端为准
+    PreferRemote = 2,
+}
+
+// 同步设置
+#[derive(Debug, Clone, Serialize, Deserialize, SignalPiece)]
+pub struct SyncConfigMsg {
+    // 冲突处理策略
+    pub conflict_policy: ConflictPolicyMsg,
+    // 回收站保留天数 为0时不自动清理
+    pub trash_days: u32,
+    // 远端覆盖或删除前保留历史版本
+    pub remote_versions: bool,
+    // 上传限速 KiB/s 为0时不限速
+    #[serde(default)]
+    pub upload_limit: u32,
+    // 下载限速 KiB/s 为0时不限速
+    #[serde(default)]
+    pub download_limit: u32,
+    // 按流量计费的网络下跳过定时同步
+    #[serde(default)]
+    pub skip_metered: bool,
+}
+
+// 查询远端历史版本
+#[derive(Debug, Serialize, Deserialize, SignalPiece)]
+pub struct RemoteVersionReqMsg {
+    pub remote_dir: String,
+    // 文件路径 为空时返回全部
+    pub file: String,
+}
+
+// 远端历史版本列表
+#[derive(Debug, Serialize, Deserialize, SignalPiece)]
+pub struct RemoteVersionsMsg {
+    // 按时间倒序
+    pub versions: Vec<RemoteVersionMsg>,
+}
+
+// 远端文件的一个历史版本
+#[derive(Debug, Clone, Serialize, Deserialize, SignalPiece)]
+pub struct RemoteVersionMsg {
+    pub file: String,
+    // 版本时间 ms 即.versions下的文件夹名
+    pub time: u64,
+    // 该版本的修改时间 ms
+    pub mtime: u64,
+    // 文件大小 未知时为0
+    pub size: u64,
+    // 文件内容sha256 未知时为空
+    pub hash: String,
+}
+
+// 还原远端历史版本
+#[derive(Debug, Serialize, Deserialize, SignalPiece)]
+pub struct RestoreRemoteVersionMsg {
+    pub remote_dir: String,
+    pub file: String,
+    // 版本时间
+    pub time: u64,
+}
+
+// 回收站文件列表
+#[derive(Debug, Serialize, Deserialize, SignalPiece)]
+pub struct SyncTrashMsg {
+    // 按时间倒序
+    pub items: Vec<SyncTrashItemMsg>,
+}
+
+// 同步时被删除或覆盖的本地文件
+#[derive(Debug, Clone, Serialize, Deserialize, SignalPiece)]
+pub struct SyncTrashItemMsg {
+    pub id: String,
+    pub remote_dir: String,
+    // 文件路径 文件夹以/结尾
+    pub file: String,
+    // 同步运行id 同一次同步相同
+    pub run_id: u64,
+    // 移入回收站时间 ms
+    pub time: u64,
+    // 文件大小 文件夹为0
+    pub size: u64,
+    pub reason: TrashReasonEnumMsg,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, SignalPiece)]
+pub enum TrashReasonEnumMsg {
+    // 同步删除
+    DELETED = 0,
+    // 下载时被覆盖
+    OVERWRITTEN = 1,
 }
 
 // 对空缺的远端目录新增本地路径
@@ -61,6 +238,20 @@ pub enum FileStatusEnumMsg {
 pub struct AddLocalForRemoteMsg {
     pub local_dir: String,
     pub remote_dir: String,
+    // 远端文件夹已加密时的密码
+    pub passphrase: String,
+    // 远端文件夹所属账户 为空时为默认账户
+    pub account: String,
+    // 只同步的子文件夹 为空时同步全部
+    pub includes: Vec<String>,
+}
+
+// 同步文件夹在本机选择同步的子文件夹
+#[derive(Debug, Serialize, Deserialize, SignalPiece)]
+pub struct SyncIncludesMsg {
+    pub remote_dir: String,
+    // 子文件夹相对路径 为空时同步全部
+    pub includes: Vec<String>,
 }
 
 // 新增同步文件夹
@@ -70,4 +261,50 @@ pub struct AddSyncDirMsg {
     pub local_dir: String,
     // tag
     pub tag: String,
-}
\ No newline at end of file
+    // 忽略规则 gitignore语法，与文件夹下的.nfignore合并生效
+    pub ignore: Vec<String>,
+    // 加密密码 为空时不加密
+    pub passphrase: String,
+    // 加密时是否同时混淆远端文件名
+    pub encrypt_names: bool,
+    // 同步到的账户 为空时为默认账户
+    pub account: String,
+}
+// 定时同步记录列表
+#[derive(Debug, Serialize, Deserialize, SignalPiece)]
+pub struct SyncRunsMsg {
+    // 按时间倒序
+    pub runs: Vec<SyncRunMsg>,
+}
+
+// 一次定时同步记录
+#[derive(Debug, Clone, Serialize, Deserialize, SignalPiece)]
+pub struct SyncRunMsg {
+    // 开始时间 ms
+    pub start_time: u64,
+    // 结束时间 ms
+    pub end_time: u64,
+    // 是否跳过本次同步
+    pub skipped: bool,
+    // 跳过的原因
+    pub reason: Option<String>,
+    // 整体失败原因(如未登录、网络错误)
+    pub error: Option<String>,
+    // 各文件夹同步结果
+    pub dirs: Vec<SyncRunDirMsg>,
+}
+
+// 单个文件夹的定时同步结果
+#[derive(Debug, Clone, Serialize, Deserialize, SignalPiece)]
+pub struct SyncRunDirMsg {
+    pub local_dir: String,
+    pub remote_dir: String,
+    // 新增的文件数量
+    pub add: u32,
+    // 删除的文件数量
+    pub del: u32,
+    // 同步的文件数量
+    pub modify: u32,
+    // 失败原因，为空时表示同步成功
+    pub error: Option<String>,
+}
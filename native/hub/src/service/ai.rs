@@ -1,15 +1,16 @@
+use crate::common::error::ServiceError;
 use crate::common::global_data::GlobalData;
 use crate::messages::ai::{
     AiModelMsg, BaiduAiKeyReqMsg, BaiduAiRspMsg, ModelEnumMsg, QuestionListMsg, QuestionMsg,
 };
 use crate::messages::common::{UintFiveMsg, VecStringMsg};
-use crate::service::service::{Service, StreamService};
+use crate::service::service::{FuncDescriptor, Service, ServiceDescriptor, StreamService, EMPTY_MSG};
 use crate::{
     async_func_nono, async_func_notype, async_func_typeno, async_stream_func_typeno, func_end,
     func_notype, func_typeno, func_typetype,
 };
 use ahash::AHashMap;
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use async_trait::async_trait;
 use bytes::Bytes;
 use futures_util::StreamExt;
@@ -91,6 +92,25 @@ const AUTH_TOKEN: &str = "SparkAiService:AUTH_TOKEN";
 const MODEL: &str = "AiService:MODEL";
 const SPARK_HISTORY: &str = "SparkAiService:HISTORY";
 
+/// 服务描述
+pub const DESCRIPTOR: ServiceDescriptor = ServiceDescriptor {
+    name: "AiService",
+    funcs: &[
+        FuncDescriptor::stream("question", "QuestionMsg", "BaiduAiRspMsg"),
+        FuncDescriptor::new("get_kv", EMPTY_MSG, "BaiduAiKeyReqMsg"),
+        FuncDescriptor::new("refresh_token", EMPTY_MSG, EMPTY_MSG),
+        FuncDescriptor::new("set_kv", "BaiduAiKeyReqMsg", EMPTY_MSG),
+        FuncDescriptor::new("set_model", "AiModelMsg", EMPTY_MSG),
+        FuncDescriptor::new("get_question_list", EMPTY_MSG, "QuestionListMsg"),
+        FuncDescriptor::new("get_model", EMPTY_MSG, "AiModelMsg"),
+        FuncDescriptor::new("get_question", "UintFiveMsg", "VecStringMsg"),
+        FuncDescriptor::new("new_question", "UintFiveMsg", EMPTY_MSG),
+        FuncDescriptor::new("del_question", "UintFiveMsg", EMPTY_MSG),
+    ],
+    platforms: &[],
+    timeout: None,
+};
+
 pub struct BaiduAiService {
     client: Client,
     gd: GlobalData,
@@ -102,6 +122,8 @@ pub struct BaiduAiService {
     // spark
     auth_token: Option<String>,
     model: AiModelEnum,
+    // 密钥是否已从系统密钥服务读取
+    secrets_loaded: bool,
 }
 
 impl BaiduAiService {
@@ -122,13 +144,25 @@ impl BaiduAiService {
         Self {
             client,
             token: None,
-            app_id: gd.get_data(APP_ID.to_string()).await,
-            secret: gd.get_data(SECRET.to_string()).await,
-            auth_token: gd.get_data(AUTH_TOKEN.to_string()).await,
+            app_id: None,
+            secret: None,
+            auth_token: None,
             gd,
             history,
             model,
+            secrets_loaded: false,
+        }
+    }
+
+    /// 首次使用时读取密钥 避免创建服务时等待密钥服务解锁
+    async fn load_secrets(&mut self) {
+        if self.secrets_loaded {
+            return;
         }
+        self.app_id = self.gd.get_secret(APP_ID.to_string()).await;
+        self.secret = self.gd.get_secret(SECRET.to_string()).await;
+        self.auth_token = self.gd.get_secret(AUTH_TOKEN.to_string()).await;
+        self.secrets_loaded = true;
     }
 }
 
@@ -140,6 +174,7 @@ impl StreamService for BaiduAiService {
         req_data: Vec<u8>,
         tx: UnboundedSender<Result<Option<Vec<u8>>>>,
     ) -> Result<()> {
+        self.load_secrets().await;
         async_stream_func_typeno!(self, func, req_data, question, QuestionMsg, tx);
         func_end!(func)
     }
@@ -148,6 +183,7 @@ impl StreamService for BaiduAiService {
 #[async_trait]
 impl Service for BaiduAiService {
     async fn handle(&mut self, func: &str, req_data: Vec<u8>) -> Result<Option<Vec<u8>>> {
+        self.load_secrets().await;
         async_func_notype!(self, func, get_kv);
         async_func_nono!(self, func, refresh_token);
         async_func_typeno!(
@@ -183,14 +219,14 @@ impl BaiduAiService {
         tx: UnboundedSender<Result<Option<Vec<u8>>>>,
     ) -> Result<()> {
         if !self.history.contains_key(&req.id) {
-            return Err(anyhow::anyhow!("没有对应的对话id"));
+            return Err(ServiceError::NotFound("没有对应的对话id".to_string()).into());
         }
         if self.model == AiModelEnum::Spark && self.auth_token.is_none() {
-            return Err(anyhow::anyhow!("请先设置spark的token"));
+            return Err(ServiceError::Config("请先设置spark的token".to_string()).into());
         }
         let current_size = req.desc.len();
         if current_size > MAX_SIZE {
-            return Err(anyhow::anyhow!("提问最大长度为8000"));
+            return Err(ServiceError::InvalidInput("提问最大长度为8000".to_string()).into());
         }
 
         // 对最大长度字符8000进行限制
@@ -331,17 +367,17 @@ impl BaiduAiService {
         if !info.starts_with("data:") {
             if model == AiModelEnum::Spark {
                 let error = serde_json::from_str::<SparkAiErrorRsp>(&info)?;
-                return Err(anyhow!(error.message));
+                return Err(ServiceError::Remote(error.message).into());
             }
             let error = serde_json::from_str::<BaiduAiErrorRsp>(&info)?;
             if error.error_code == 336002 {
-                return Err(anyhow::anyhow!("token已失效,请手动刷新token"));
+                return Err(ServiceError::NotLoggedIn("token已失效,请手动刷新token".to_string()).into());
             } else if error.error_code == 110 {
-                return Err(anyhow::anyhow!("token错误，请重新设置密钥"));
+                return Err(ServiceError::Config("token错误，请重新设置密钥".to_string()).into());
             } else if error.error_msg.contains("limit") {
-                return Err(anyhow::anyhow!("接口调用量超限，请稍后重试"));
+                return Err(ServiceError::Remote("接口调用量超限，请稍后重试".to_string()).into());
             }
-            return Err(anyhow::anyhow!(error.error_msg));
+            return Err(ServiceError::Remote(error.error_msg).into());
         }
         if model == AiModelEnum::Spark {
             if info.trim() == "data: [DONE]" || info.trim().is_empty() {
@@ -359,7 +395,7 @@ impl BaiduAiService {
                 }
                 Ok(Some(BaiduAiRspMsg { content: sb }))
             } else {
-                Err(anyhow::anyhow!(rsp.message))
+                Err(ServiceError::Remote(rsp.message).into())
             }
         } else {
             let rsp: BaiduAiRsp = serde_json::from_str(info.trim_start_matches("data:"))?;
@@ -374,23 +410,23 @@ impl BaiduAiService {
     /// 刷新token
     async fn refresh_token(&mut self) -> Result<()> {
         if self.model == AiModelEnum::Spark {
-            return Err(anyhow::anyhow!("spark模型不支持token刷新"));
+            return Err(ServiceError::InvalidInput("spark模型不支持token刷新".to_string()).into());
         }
-        let app_id = self.app_id.as_ref().ok_or(anyhow::anyhow!("appid未设置"))?;
+        let app_id = self.app_id.as_ref().ok_or(ServiceError::Config("appid未设置".to_string()))?;
         let secret = self
             .secret
             .as_ref()
-            .ok_or(anyhow::anyhow!("secret未设置"))?;
+            .ok_or(ServiceError::Config("secret未设置".to_string()))?;
         let rsp = self.client
             .post(&format!("https://aip.baidubce.com/oauth/2.0/token?grant_type=client_credentials&client_id={app_id}&client_secret={secret}"))
             .header("Content-Type", "application/json")
             .send().await?;
         let token = rsp.json::<BaiduTokenRsp>().await?;
         if token.error_description.is_some() {
-            return Err(anyhow::anyhow!(token.error_description.unwrap()));
+            return Err(ServiceError::Remote(token.error_description.unwrap()).into());
         }
 
-        self.token = Some(token.access_token.ok_or(anyhow::anyhow!("无法获取token"))?);
+        self.token = Some(token.access_token.ok_or(ServiceError::Remote("无法获取token".to_string()))?);
         Ok(())
     }
 
@@ -399,7 +435,7 @@ impl BaiduAiService {
         if self.model == AiModelEnum::Spark {
             self.auth_token = Some(req.api_key);
             self.gd
-                .set_data(AUTH_TOKEN.to_string(), &self.auth_token.as_ref().unwrap())
+                .set_secret(AUTH_TOKEN.to_string(), self.auth_token.as_ref().unwrap())
                 .await?;
             return Ok(());
         }
@@ -408,10 +444,10 @@ impl BaiduAiService {
         self.secret = Some(req.secret);
         self.refresh_token().await?;
         self.gd
-            .set_data(APP_ID.to_string(), &self.app_id.as_ref().unwrap())
+            .set_secret(APP_ID.to_string(), self.app_id.as_ref().unwrap())
             .await?;
         self.gd
-            .set_data(SECRET.to_string(), &self.secret.as_ref().unwrap())
+            .set_secret(SECRET.to_string(), self.secret.as_ref().unwrap())
             .await?;
         Ok(())
     }
@@ -420,7 +456,7 @@ impl BaiduAiService {
     async fn get_kv(&mut self) -> Result<BaiduAiKeyReqMsg> {
         if self.model == AiModelEnum::Spark {
             if self.auth_token.is_none() {
-                return Err(anyhow!("需先设置appid"));
+                return Err(ServiceError::Config("需先设置appid".to_string()).into());
             }
             return Ok(BaiduAiKeyReqMsg {
                 api_key: self.auth_token.as_ref().unwrap().clone(),
@@ -447,7 +483,7 @@ impl BaiduAiService {
     async fn set_model(&mut self, model: AiModelMsg) -> Result<()> {
         let model = match ModelEnumMsg::try_from(model.model_enum) {
             Err(_e) => {
-                return Err(anyhow::anyhow!("无法获取model"));
+                return Err(ServiceError::InvalidInput("无法获取model".to_string()).into());
             }
             Ok(v) => v,
         };
@@ -493,14 +529,14 @@ impl BaiduAiService {
         let result = self
             .history
             .get(&req.value)
-            .ok_or(anyhow::anyhow!("无法找到对应id"))?;
+            .ok_or(ServiceError::NotFound("无法找到对应id".to_string()))?;
         let result = result.into_iter().map(|x| x.to_string()).collect();
         Ok(VecStringMsg { values: result })
     }
 
     fn new_question(&mut self, req: UintFiveMsg) -> Result<()> {
         if self.history.contains_key(&req.value) {
-            return Err(anyhow::anyhow!("已存在对应的对话id"));
+            return Err(ServiceError::InvalidInput("已存在对应的对话id".to_string()).into());
         }
         self.history.insert(req.value, Vec::new());
         Ok(())
@@ -508,7 +544,7 @@ impl BaiduAiService {
 
     fn del_question(&mut self, req: UintFiveMsg) -> Result<()> {
         if self.history.remove(&req.value).is_none() {
-            return Err(anyhow::anyhow!("不存在对应的对话id"));
+            return Err(ServiceError::NotFound("不存在对应的对话id".to_string()).into());
         }
         Ok(())
     }
@@ -1,3 +1,35 @@
+use crate::service::service::{FuncDescriptor, FuncTimeout, ServiceDescriptor, EMPTY_MSG};
+use std::time::Duration;
+
+/// 亮度服务描述
+pub const LIGHT_DESCRIPTOR: ServiceDescriptor = ServiceDescriptor {
+    name: "DisplayLightService",
+    funcs: &[
+        // 逐个读取显示器亮度 显示器较多时耗时更长
+        FuncDescriptor::new("get_all_devices", EMPTY_MSG, "DisplayInfoReqMsg")
+            .timeout(FuncTimeout::After(Duration::from_secs(30))),
+        FuncDescriptor::new("set_light", "DisplayInfoMsg", EMPTY_MSG),
+    ],
+    // windows下使用winapi，linux下使用i2c DDC
+    platforms: &["linux", "windows"],
+    timeout: Some(Duration::from_secs(10)),
+};
+
+/// 显示模式服务描述
+pub const MODE_DESCRIPTOR: ServiceDescriptor = ServiceDescriptor {
+    name: "DisplayModeService",
+    funcs: &[
+        FuncDescriptor::new("get_system_mode", EMPTY_MSG, "SystemModeMsg"),
+        FuncDescriptor::new("get_wallpaper", EMPTY_MSG, "GetWallpaperRspMsg"),
+        FuncDescriptor::new("get_current_mode", EMPTY_MSG, "GetDisplayModeRspMsg"),
+        FuncDescriptor::new("get_system_color", EMPTY_MSG, "UintFiveMsg"),
+        FuncDescriptor::new("set_mode", "DisplayModeMsg", EMPTY_MSG),
+        FuncDescriptor::new("set_system_mode", "SystemModeMsg", EMPTY_MSG),
+    ],
+    platforms: &["linux", "windows"],
+    timeout: Some(Duration::from_secs(30)),
+};
+
 #[cfg(target_os = "windows")]
 pub mod display_os {
     use crate::common::global_data::GlobalData;
@@ -274,11 +306,15 @@ pub mod display_os {
 
 #[cfg(target_os = "linux")]
 pub mod display_os {
+    use crate::common::event_bus::{topic, EventBus};
     use crate::common::global_data::GlobalData;
     use crate::common::APP_NAME;
     use crate::dbus::power_manager::OrgFreedesktopPowerManagementInhibit;
-    use crate::dbus::wallpaper::OrgKdePlasmaShell;
+    use crate::dbus::wallpaper::{
+        OrgKdePlasmaShell, OrgKdePlasmaShellColorChanged, OrgKdePlasmaShellWallpaperChanged,
+    };
     use crate::messages::common::UintFiveMsg;
+    use crate::messages::event::{ColorChangedMsg, MonitorChangedMsg, WallpaperChangedMsg};
     use crate::messages::display::{
         DisplayInfoMsg, DisplayInfoReqMsg, GetDisplayModeRspMsg, GetWallpaperRspMsg,
         SystemModeMsg,
@@ -289,7 +325,8 @@ pub mod display_os {
     use anyhow::{Error, Result};
     use async_trait::async_trait;
     use dbus::arg::RefArg;
-    use dbus::nonblock::{Proxy, SyncConnection};
+    use dbus::message::SignalArgs;
+    use dbus::nonblock::{MsgMatch, Proxy, SyncConnection};
     use dbus_tokio::connection;
     use ddc::Ddc;
     use ddc_i2c::I2cDeviceDdc;
@@ -299,6 +336,7 @@ pub mod display_os {
     use std::time::Duration;
     use tokio::fs::{read_dir, File};
     use tokio::io::AsyncReadExt;
+    use tokio::task::AbortHandle;
     use tokio_stream::wrappers::ReadDirStream;
     use tokio_stream::StreamExt;
     use xdg::BaseDirectories;
@@ -426,6 +464,8 @@ pub mod display_os {
         }
     }
 
+    // 显示器连接状态检测间隔
+    const MONITOR_INTERVAL: Duration = Duration::from_secs(3);
     const POWER_MARK: &str = "displayMode:powerManage:linux";
     const SCREENSAVER_MARK: &str = "displayMode:screensaverManage:linux";
     /// 显示壁纸
@@ -442,6 +482,10 @@ pub mod display_os {
         power_id: Option<u32>,
         // 锁屏id
         screen_saver_id: Option<u32>,
+        // 主题色及壁纸变化监听
+        signal_matches: Vec<MsgMatch>,
+        // 显示器插拔监听
+        monitor_watcher: AbortHandle,
     }
 
     #[async_trait]
@@ -467,6 +511,20 @@ pub mod display_os {
             );
             func_end!(func)
         }
+
+        async fn close(&mut self) -> Result<()> {
+            self.monitor_watcher.abort();
+            for signal_match in self.signal_matches.drain(..) {
+                self.proxy.connection.remove_match(signal_match.token()).await?;
+            }
+            Ok(())
+        }
+    }
+
+    impl Drop for DisplayMode {
+        fn drop(&mut self) {
+            self.monitor_watcher.abort();
+        }
     }
 
     impl DisplayMode {
@@ -501,9 +559,13 @@ pub mod display_os {
                 "org.freedesktop.ScreenSaver",
                 "/org/freedesktop/ScreenSaver",
                 Duration::from_secs(2),
-                conn,
+                conn.clone(),
             );
 
+            // 3. 监听事件
+            let signal_matches = Self::listen_signals(&conn, global_data.event_bus()).await?;
+            let monitor_watcher = Self::watch_monitors(global_data.event_bus().clone());
+
             // 加载是否休眠设置
             let enabled: bool = global_data.get_data(POWER_MARK.to_string()).await.unwrap_or_default();
             let mut this = Self {
@@ -513,7 +575,9 @@ pub mod display_os {
                 power_manage_proxy,
                 screen_saver_manage_proxy,
                 power_id: None,
-                screen_saver_id: None
+                screen_saver_id: None,
+                signal_matches,
+                monitor_watcher,
             };
             if enabled {
                 // 忽略执行错误
@@ -642,6 +706,89 @@ pub mod display_os {
     }
 
     impl DisplayMode {
+        /// 监听KDE主题色及壁纸变化
+        async fn listen_signals(
+            conn: &Arc<SyncConnection>,
+            event_bus: &EventBus,
+        ) -> Result<Vec<MsgMatch>> {
+            let bus = event_bus.clone();
+            let color = conn
+                .add_match(OrgKdePlasmaShellColorChanged::match_rule(None, None))
+                .await?
+                .cb(move |_, signal: OrgKdePlasmaShellColorChanged| {
+                    bus.publish(
+                        topic::COLOR_CHANGED,
+                        &ColorChangedMsg {
+                            color: signal.changed_color,
+                        },
+                    );
+                    true
+                });
+            let bus = event_bus.clone();
+            let wallpaper = conn
+                .add_match(OrgKdePlasmaShellWallpaperChanged::match_rule(None, None))
+                .await?
+                .cb(move |_, signal: OrgKdePlasmaShellWallpaperChanged| {
+                    bus.publish(
+                        topic::WALLPAPER_CHANGED,
+                        &WallpaperChangedMsg {
+                            screen: signal.screen_num,
+                        },
+                    );
+                    true
+                });
+            Ok(vec![color, wallpaper])
+        }
+
+        /// 定时检测显示器连接状态，变化时发布事件
+        fn watch_monitors(event_bus: EventBus) -> AbortHandle {
+            tokio::spawn(async move {
+                let mut last = Self::connected_monitors().await;
+                let mut interval = tokio::time::interval(MONITOR_INTERVAL);
+                loop {
+                    interval.tick().await;
+                    let monitors = Self::connected_monitors().await;
+                    if monitors != last {
+                        event_bus.publish(
+                            topic::MONITOR_CHANGED,
+                            &MonitorChangedMsg {
+                                monitors: monitors.clone(),
+                            },
+                        );
+                        last = monitors;
+                    }
+                }
+            })
+            .abort_handle()
+        }
+
+        /// 获取已连接的显示器
+        async fn connected_monitors() -> Vec<String> {
+            let Ok(entries) = read_dir(DRM_PATH).await else {
+                return Vec::new();
+            };
+            let mut entries = ReadDirStream::new(entries);
+            let mut result = Vec::new();
+            while let Some(Ok(entry)) = entries.next().await {
+                let file_name = entry.file_name();
+                let Some((card, name)) = file_name.to_str().and_then(|x| x.split_once('-')) else {
+                    continue;
+                };
+                if !card.starts_with("card") {
+                    continue;
+                }
+                let mut path = entry.path();
+                path.push("status");
+                if let Ok(status) = tokio::fs::read_to_string(path).await
+                    && status.trim() == "connected"
+                {
+                    result.push(name.to_string());
+                }
+            }
+            result.sort();
+            result
+        }
+
         // 禁止系统休眠
         async fn inhabit(&mut self) -> Result<()> {
             if self.power_id.is_some() {
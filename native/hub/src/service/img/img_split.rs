@@ -1,7 +1,7 @@
 use std::cmp::max;
 use std::io::{Read, Write};
 use std::sync::Arc;
-use crate::service::service::Service;
+use crate::service::service::{FuncDescriptor, Service, ServiceDescriptor, EMPTY_MSG};
 use opencv::prelude::*;
 use opencv::{core, imgcodecs, imgproc};
 use crate::{async_func_nono, async_func_notype, async_func_typeno, async_func_typetype, func_end, func_nono, func_typeno};
@@ -24,6 +24,19 @@ pub struct ImageSplitService {
     scale: f64,
 }
 
+/// 服务描述
+pub const DESCRIPTOR: ServiceDescriptor = ServiceDescriptor {
+    name: "ImageSplitService",
+    funcs: &[
+        FuncDescriptor::new("create_image", "DataMsg", EMPTY_MSG),
+        FuncDescriptor::new("clear", EMPTY_MSG, EMPTY_MSG),
+        FuncDescriptor::new("handle_image", "ImageSplitReqMsg", "DataMsg"),
+        FuncDescriptor::new("preview_image", EMPTY_MSG, "DataMsg"),
+    ],
+    platforms: &[],
+    timeout: None,
+};
+
 #[async_trait::async_trait]
 impl Service for ImageSplitService {
     async fn handle(&mut self, func: &str, req_data: Vec<u8>) -> Result<Option<Vec<u8>>> {
@@ -1,8 +1,9 @@
+use crate::common::error::ServiceError;
 use crate::common::global_data::GlobalData;
 use crate::messages::common::{DataMsg, StringMsg, VecStringMsg};
 use crate::messages::tar_pdf::{OcrConfigMsg, OcrDataMsg, RefOcrDatasMsg, RenameFileMsg, SimilarityMsg, SimilarityResultMsg, TarPdfMsg, TarPdfResultMsg, TarPdfResultsMsg};
-use crate::service::service::{Service, StreamService};
-use crate::{async_func_nono, async_func_notype, async_func_typetype, async_stream_func_typeno, func_end, func_nono, func_notype, func_typeno, func_typetype};
+use crate::service::service::{FuncDescriptor, FuncTimeout, ImmService, Progress, Service, ServiceDescriptor, StreamService, EMPTY_MSG};
+use crate::{async_func_nono, async_func_notype, async_func_typetype, async_progress_func_typetype, async_stream_func_typeno, func_end, func_nono, func_notype, func_typeno, func_typetype};
 use anyhow::{anyhow, Result};
 use futures_util::StreamExt;
 use pdfium::{set_library_location, PdfiumDocument, PdfiumRenderConfig};
@@ -12,12 +13,14 @@ use serde_json::Value;
 use std::collections::HashMap;
 use std::io::Cursor;
 use std::path::{Path, PathBuf};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use ahash::{AHashMap, AHashSet};
 use calamine::{Data, Reader, Xlsx};
 use image::DynamicImage;
 use log::{debug, warn};
 use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::OnceCell;
 use tokio_stream::wrappers::ReadDirStream;
 use crate::common::utils::{index_to_string, path_to_file_name, path_to_string};
 use crate::service::pdf::ocr::{OcrData, OcrResult};
@@ -27,11 +30,13 @@ use crate::service::pdf::str_format::FormatString;
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 struct OcrConfig {
-    // pdf 密码
+    // pdf 密码 保存在系统密钥服务中
+    #[serde(default, skip_serializing)]
     pdf_password: Option<String>,
     // ocr识别url
     url: String,
-    // ocr识别密钥
+    // ocr识别密钥 保存在系统密钥服务中
+    #[serde(default, skip_serializing)]
     api_key: String,
 }
 
@@ -96,8 +101,10 @@ struct OcrPdfData {
 pub struct TarPdfService {
     // 数据库配置
     global_data: GlobalData,
-    // ocr识别配置
-    config: OcrConfig,
+    // ocr识别配置 与只读处理器共享
+    config: Arc<RwLock<OcrConfig>>,
+    // 密钥是否已从系统密钥服务读取 与只读处理器共享
+    secrets: Arc<OnceCell<()>>,
     // 参考数据
     ref_data: Option<RefData>,
     // 导出配置
@@ -106,7 +113,43 @@ pub struct TarPdfService {
     ocr_data: Vec<OcrPdfData>
 }
 
+/// 只读请求处理 不需要等待识别等耗时操作
+struct TarPdfReader {
+    global_data: GlobalData,
+    config: Arc<RwLock<OcrConfig>>,
+    secrets: Arc<OnceCell<()>>,
+}
+
 const CONFIG_CACHE: &str = "tarPdfConfig";
+const API_KEY_SECRET: &str = "tarPdfConfig.apiKey";
+const PDF_PASSWORD_SECRET: &str = "tarPdfConfig.pdfPassword";
+// 单次OCR请求超时时间 服务无响应时该文件识别失败，不阻塞整批任务
+const OCR_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// 服务描述
+pub const DESCRIPTOR: ServiceDescriptor = ServiceDescriptor {
+    name: "TarPdfService",
+    funcs: &[
+        // 批量识别耗时取决于文件数量 单个文件的识别由OCR请求超时限制
+        FuncDescriptor::stream("handle", "VecStringMsg", "TarPdfMsg").timeout(FuncTimeout::Unlimited),
+        FuncDescriptor::new("set_config", "OcrConfigMsg", EMPTY_MSG),
+        FuncDescriptor::new("set_ref_config_tags", "VecStringMsg", EMPTY_MSG),
+        FuncDescriptor::read("get_config", EMPTY_MSG, "OcrConfigMsg"),
+        FuncDescriptor::new("get_ocr_pdf_data", EMPTY_MSG, "TarPdfResultsMsg"),
+        FuncDescriptor::new("set_ref_config_template", "StringMsg", "StringMsg"),
+        FuncDescriptor::new("reset", EMPTY_MSG, EMPTY_MSG),
+        FuncDescriptor::new("ocr_check", EMPTY_MSG, EMPTY_MSG),
+        FuncDescriptor::new("export_excel", EMPTY_MSG, "StringMsg"),
+        FuncDescriptor::new("scan_pdf", "StringMsg", "VecStringMsg"),
+        FuncDescriptor::new("get_pdf_cover", "StringMsg", "DataMsg"),
+        FuncDescriptor::new("set_ref_config", "StringMsg", "RefOcrDatasMsg"),
+        FuncDescriptor::new("rename_by_excel", "StringMsg", "RenameFileMsg"),
+        FuncDescriptor::new("similar_pdf", "SimilarityMsg", "SimilarityResultMsg")
+            .timeout(FuncTimeout::Unlimited),
+    ],
+    platforms: &[],
+    timeout: Some(Duration::from_secs(120)),
+};
 
 #[async_trait::async_trait]
 impl StreamService for TarPdfService {
@@ -116,6 +159,7 @@ impl StreamService for TarPdfService {
         req_data: Vec<u8>,
         tx: UnboundedSender<Result<Option<Vec<u8>>>>,
     ) -> Result<()> {
+        load_secrets(&self.global_data, &self.config, &self.secrets).await;
         async_stream_func_typeno!(self, func, req_data, handle, PdfFilesMsg, tx);
         func_end!(func)
     }
@@ -124,66 +168,99 @@ impl StreamService for TarPdfService {
 #[async_trait::async_trait]
 impl Service for TarPdfService {
     async fn handle(&mut self, func: &str, req_data: Vec<u8>) -> Result<Option<Vec<u8>>> {
+        load_secrets(&self.global_data, &self.config, &self.secrets).await;
         func_typeno!(self, func, req_data, set_config, OcrConfigMsg, set_ref_config_tags, VecStringMsg);
-        func_notype!(self, func, get_config, get_ocr_pdf_data);
+        func_notype!(self, func, get_ocr_pdf_data);
         func_typetype!(self, func, req_data, set_ref_config_template, StringMsg);
         func_nono!(self, func, reset);
         async_func_nono!(self, func, ocr_check);
         async_func_notype!(self, func, export_excel);
         async_func_typetype!(self, func, req_data, scan_pdf, StringMsg, get_pdf_cover, StringMsg,
-            set_ref_config, StringMsg, rename_by_excel, StringMsg, similar_pdf, SimilarityMsg);
+            set_ref_config, StringMsg, rename_by_excel, StringMsg);
 
         func_end!(func)
     }
 
+    async fn handle_progress(&mut self, func: &str, req_data: Vec<u8>, progress: Progress) -> Result<Option<Vec<u8>>> {
+        load_secrets(&self.global_data, &self.config, &self.secrets).await;
+        async_progress_func_typetype!(self, func, req_data, similar_pdf, SimilarityMsg, progress);
+        Service::handle(self, func, req_data).await
+    }
+
     async fn close(&mut self) -> Result<()> {
-        if self.config.has_data() {
-            self.global_data
-                .set_data(CONFIG_CACHE.to_string(), &self.config)
-                .await?;
+        let config = self.config();
+        if config.has_data() {
+            save_config(&self.global_data, &config).await?;
         }
         Ok(())
     }
+
+    fn read_handler(&self) -> Option<Arc<Box<dyn ImmService>>> {
+        Some(Arc::new(Box::new(TarPdfReader {
+            global_data: self.global_data.clone(),
+            config: self.config.clone(),
+            secrets: self.secrets.clone(),
+        })))
+    }
+}
+
+#[async_trait::async_trait]
+impl ImmService for TarPdfReader {
+    async fn handle(&self, func: &str, _req_data: Vec<u8>) -> Result<Option<Vec<u8>>> {
+        load_secrets(&self.global_data, &self.config, &self.secrets).await;
+        func_notype!(self, func, get_config);
+        func_end!(func)
+    }
+}
+
+impl TarPdfReader {
+    fn get_config(&self) -> Result<OcrConfigMsg> {
+        let config = self.config.read().unwrap();
+        if config.has_data() {
+            Ok(config.clone().into())
+        } else {
+            Err(ServiceError::Config("请先设置OCR配置".to_string()).into())
+        }
+    }
 }
 
 impl TarPdfService {
     fn set_config(&mut self, config: OcrConfigMsg) -> Result<()> {
         let config = OcrConfig::from(config);
         if !config.has_data() {
-            Err(anyhow!("配置不正确"))
+            Err(ServiceError::Config("配置不正确".to_string()).into())
         } else {
-            self.config = config;
+            *self.config.write().unwrap() = config;
             Ok(())
         }
     }
 
-    fn get_config(&self) -> Result<OcrConfigMsg> {
-        if self.config.has_data() {
-            Ok(self.config.clone().into())
-        } else {
-            Err(anyhow!("请先设置OCR配置"))
-        }
+    /// 当前ocr配置
+    fn config(&self) -> OcrConfig {
+        self.config.read().unwrap().clone()
     }
 
     const ERROR_MSG: &'static str = "无法探测出OCR服务器，请检查配置";
     async fn ocr_check(&self) -> Result<()> {
-        if !self.config.has_data() {
-            return Err(anyhow!("请先设置OCR配置"));
+        let config = self.config();
+        if !config.has_data() {
+            return Err(ServiceError::Config("请先设置OCR配置".to_string()).into());
         }
 
-        let url = format!("{}/check", &self.config.url);
+        let url = format!("{}/check", &config.url);
         let rsp = reqwest::Client::new()
             .post(url)
-            .header("api-key", &self.config.api_key)
+            .header("api-key", &config.api_key)
+            .timeout(OCR_TIMEOUT)
             .send()
             .await?
             .text()
             .await?;
         let rsp: Value = serde_json::from_str(&rsp)?;
-        let r = rsp.get("result").ok_or_else(|| anyhow!(Self::ERROR_MSG))?;
-        let r = r.as_str().ok_or_else(|| anyhow!(Self::ERROR_MSG))?;
+        let r = rsp.get("result").ok_or_else(|| ServiceError::Remote(Self::ERROR_MSG.to_string()))?;
+        let r = r.as_str().ok_or_else(|| ServiceError::Remote(Self::ERROR_MSG.to_string()))?;
         if r != "pass" {
-            return Err(anyhow!(Self::ERROR_MSG));
+            return Err(ServiceError::Remote(Self::ERROR_MSG.to_string()).into());
         }
         Ok(())
     }
@@ -196,7 +273,7 @@ impl TarPdfService {
         // 1. 配置检查
         self.ocr_check().await?;
         if self.ref_data.is_none() || self.ref_config.tags.is_empty() || self.ref_config.template.is_empty() {
-            return Err(anyhow!("请先设置参考数据"));
+            return Err(ServiceError::Config("请先设置参考数据".to_string()).into());
         }
 
         // 2. 转换files文件为path,并排序
@@ -205,7 +282,7 @@ impl TarPdfService {
             for file in pdf_files.values {
                 let path = PathBuf::from(file);
                 if path.is_dir() || !path.exists() {
-                    return Err(anyhow!("文件「{}」不存在", path.as_os_str().to_str().unwrap()));
+                    return Err(ServiceError::NotFound(format!("文件「{}」不存在", path.as_os_str().to_str().unwrap())).into());
                 }
                 if path.is_file() {
                     files.push(path.to_path_buf());
@@ -219,7 +296,7 @@ impl TarPdfService {
 
         // 3. handle
         self.ocr_data.clear();
-        let url = self.config.ocr_url();
+        let url = self.config().ocr_url();
         let count = pdf_files.len();
         let mut order = 1;
         let template = str_format::parser_template(&self.ref_config.template)?;
@@ -270,7 +347,7 @@ impl TarPdfService {
     /// return 导出后的文件
     async fn export_excel(&self) -> Result<StringMsg> {
         if self.ocr_data.is_empty() {
-            return Err(anyhow!("无识别结果"));
+            return Err(ServiceError::NotFound("无识别结果".to_string()).into());
         }
 
         let file = self.write_excel_file().await?;
@@ -281,22 +358,22 @@ impl TarPdfService {
     async fn rename_by_excel(&self, xlsx_file: StringMsg) -> Result<RenameFileMsg> {
         // 1.解析excel
         let mut workbook: Xlsx<_> = calamine::open_workbook(&xlsx_file.value)?;
-        let range = workbook.worksheet_range_at(0).ok_or_else(|| anyhow!("无法打开Sheet"))??;
+        let range = workbook.worksheet_range_at(0).ok_or_else(|| ServiceError::InvalidInput("无法打开Sheet".to_string()))??;
 
         let mut files = Vec::new();
         for (row_index, row) in range.rows().enumerate() {
             // 1. 检查首行数据是否正确
             if row_index == 0 {
                 if row.len() < 2 {
-                    return Err(anyhow!("格式不正确,请不要修改首行数据"));
+                    return Err(ServiceError::InvalidInput("格式不正确,请不要修改首行数据".to_string()).into());
                 }
                 if let Ok(d) = Self::read_cell_as_string(row, 0) && d == "原始文件" {
                 } else {
-                    return Err(anyhow!("格式不正确,请不要修改首行数据"));
+                    return Err(ServiceError::InvalidInput("格式不正确,请不要修改首行数据".to_string()).into());
                 }
                 if let Ok(d) = Self::read_cell_as_string(row, 1) && d == "命名结果" {
                 } else {
-                    return Err(anyhow!("格式不正确,请不要修改首行数据"));
+                    return Err(ServiceError::InvalidInput("格式不正确,请不要修改首行数据".to_string()).into());
                 }
                 continue;
             }
@@ -330,11 +407,11 @@ impl TarPdfService {
 
     // 读取单元格数据
     fn read_cell_as_string(row: &[Data], index: usize) -> Result<&String> {
-        let data = row.get(index).ok_or_else(|| anyhow!("不能读取列： {}", index))?;
+        let data = row.get(index).ok_or_else(|| ServiceError::InvalidInput(format!("不能读取列： {}", index)))?;
         if let Data::String(data) = data {
             return Ok(data);
         }
-        Err(anyhow!("无法单元格为string"))
+        Err(ServiceError::InvalidInput("无法单元格为string".to_string()).into())
     }
 
     /// 重置数据
@@ -353,7 +430,7 @@ impl TarPdfService {
     async fn scan_pdf(&self, pdf_dir: StringMsg) -> Result<VecStringMsg> {
         let pdf_dir = PathBuf::from(pdf_dir.value);
         if !pdf_dir.exists() || !pdf_dir.is_dir() {
-            return Err(anyhow!("pdf目录不存在或非目录"));
+            return Err(ServiceError::InvalidInput("pdf目录不存在或非目录".to_string()).into());
         }
         let mut pdf_files = get_pdf_files_in_directory(&pdf_dir).await?;
         sort_pdf_files(&mut pdf_files).await?;
@@ -364,14 +441,14 @@ impl TarPdfService {
 
     /// 获取pdf封面
     async fn get_pdf_cover(&self, pdf_file: StringMsg) -> Result<DataMsg> {
-        if !self.config.has_data() {
-            return Err(anyhow!("请先设置OCR配置"));
+        if !self.config().has_data() {
+            return Err(ServiceError::Config("请先设置OCR配置".to_string()).into());
         }
         let pdf = PathBuf::from(pdf_file.value);
         if !pdf.exists() || !pdf.is_file() {
-            return Err(anyhow!("pdf文件不存在或非文件"));
+            return Err(ServiceError::NotFound("pdf文件不存在或非文件".to_string()).into());
         }
-        let pdf_password = self.config.pdf_password.clone();
+        let pdf_password = self.config().pdf_password.clone();
         let (img, _pages) = tokio::task::spawn_blocking(move || {
             export_pdf_to_jpegs(&pdf, pdf_password.as_deref())
         }).await??;
@@ -383,17 +460,17 @@ impl TarPdfService {
     /// 设置参考文件
     async fn set_ref_config(&mut self, ref_image_file: StringMsg) -> Result<RefOcrDatasMsg> {
         // 1. 基本检查
-        if !self.config.has_data() {
-            return Err(anyhow!("请先设置OCR配置"));
+        if !self.config().has_data() {
+            return Err(ServiceError::Config("请先设置OCR配置".to_string()).into());
         }
         let pdf = PathBuf::from(ref_image_file.value);
         if !pdf.exists() || !pdf.is_file() {
-            return Err(anyhow!("pdf文件不存在或非文件"));
+            return Err(ServiceError::NotFound("pdf文件不存在或非文件".to_string()).into());
         }
 
         // 2. 识别数据
         let (img, _pages) = self.convert_pdf_to_img(&pdf).await?;
-        let ocr_result = self.ocr_pdf(&img, &self.config.ocr_url()).await?;
+        let ocr_result = self.ocr_pdf(&img, &self.config().ocr_url()).await?;
         let data = ocr_result.result.into_ocr_data();
 
         // 3. 转换数据
@@ -432,10 +509,10 @@ impl TarPdfService {
     /// 设置模板
     fn set_ref_config_template(&mut self, template: StringMsg) -> Result<StringMsg> {
         if self.ref_config.tags.is_empty() {
-            return Err(anyhow!("请先设置参考tags"));
+            return Err(ServiceError::Config("请先设置参考tags".to_string()).into());
         }
         if self.ref_data.is_none() {
-            return Err(anyhow!("请先设置参考文件"));
+            return Err(ServiceError::Config("请先设置参考文件".to_string()).into());
         }
 
         let mut data_map = HashMap::new();
@@ -444,7 +521,7 @@ impl TarPdfService {
             if tag == "pages" || tag == "order"{
                 continue;
             }
-            data_map.insert(tag.clone(), ref_data.image_ocr.get(tag).ok_or_else(|| anyhow!("没有找到tag: {}", tag))?.text.clone());
+            data_map.insert(tag.clone(), ref_data.image_ocr.get(tag).ok_or_else(|| ServiceError::NotFound(format!("没有找到tag: {}", tag)))?.text.clone());
         }
 
         data_map.insert("pages".to_string(), 10.to_string());
@@ -459,18 +536,20 @@ impl TarPdfService {
     }
 
     /// 文件相似性结果
-    async fn similar_pdf(&self, similar_pdf: SimilarityMsg) -> Result<SimilarityResultMsg> {
+    async fn similar_pdf(&self, similar_pdf: SimilarityMsg, progress: Progress) -> Result<SimilarityResultMsg> {
         // 1. 计算参考文件数据
         let path = PathBuf::from(&similar_pdf.ref_pdf);
         if !path.exists() {
-            return Err(anyhow!("参考文件{}不存在", similar_pdf.ref_pdf));
+            return Err(ServiceError::NotFound(format!("参考文件{}不存在", similar_pdf.ref_pdf)).into());
         }
         let (img, _) = self.convert_pdf_to_img(&path).await?;
         let ref_orb = OrbFeature::from(&img)?;
 
         // 2. 分别计算各个文件相似度
+        let total = similar_pdf.files.len() as u32;
         let mut result = Vec::with_capacity(similar_pdf.files.len());
-        for pdf in similar_pdf.files {
+        for (index, pdf) in similar_pdf.files.into_iter().enumerate() {
+            progress.report(index as u32 + 1, total, path_to_file_name(Path::new(&pdf)).unwrap_or_default());
             match self.cal_similar(&ref_orb, &pdf).await {
                 Ok(similarity) => {
                     result.push((similarity, String::with_capacity(0)));
@@ -491,6 +570,7 @@ impl TarPdfService {
 
 impl TarPdfService {
     pub async fn new(global_data: GlobalData) -> Self {
+        // 密钥首次使用时读取
         let config: OcrConfig = global_data
             .get_data(CONFIG_CACHE.to_string())
             .await
@@ -498,7 +578,8 @@ impl TarPdfService {
 
         TarPdfService {
             global_data,
-            config,
+            config: Arc::new(RwLock::new(config)),
+            secrets: Arc::new(OnceCell::new()),
             ref_data: None,
             ref_config: Default::default(),
             ocr_data: Default::default(),
@@ -582,7 +663,7 @@ impl TarPdfService {
 
     /// convert pdf to img
     async fn convert_pdf_to_img(&self, pdf: &Path) -> Result<(DynamicImage, usize)> {
-        let pdf_password = self.config.pdf_password.clone();
+        let pdf_password = self.config().pdf_password.clone();
         let pdf_file = pdf.to_path_buf();
         let (img, pages) = tokio::task::spawn_blocking(move || {
             export_pdf_to_jpegs(&pdf_file, pdf_password.as_deref())
@@ -599,8 +680,9 @@ impl TarPdfService {
             .part("file", part);
         let result = reqwest::Client::new()
             .post(url)
-            .header("api-key", &self.config.api_key)
+            .header("api-key", &self.config().api_key)
             .multipart(form)
+            .timeout(OCR_TIMEOUT)
             .send()
             .await?;
         let text = result.text().await?;
@@ -818,4 +900,45 @@ fn img_to_buf(img: &DynamicImage) -> Result<Vec<u8>> {
     Ok(buf.into_inner())
 }
 
+/// 首次使用时读取ocr密钥 避免创建服务时等待密钥服务解锁
+/// 旧版本明文保存的密钥迁移到系统密钥服务，失败时只在内存中使用
+async fn load_secrets(global_data: &GlobalData, config: &RwLock<OcrConfig>, loaded: &OnceCell<()>) {
+    loaded
+        .get_or_init(|| async {
+            let legacy = {
+                let config = config.read().unwrap();
+                (!config.api_key.is_empty() || config.pdf_password.is_some()).then(|| config.clone())
+            };
+            if let Some(legacy) = legacy {
+                if let Err(e) = save_config(global_data, &legacy).await {
+                    warn!("迁移ocr配置密钥失败: {}", e);
+                }
+                return;
+            }
+            let api_key = global_data
+                .get_secret(API_KEY_SECRET.to_string())
+                .await
+                .unwrap_or_default();
+            let pdf_password = global_data.get_secret(PDF_PASSWORD_SECRET.to_string()).await;
+            let mut config = config.write().unwrap();
+            config.api_key = api_key;
+            config.pdf_password = pdf_password;
+        })
+        .await;
+}
 
+/// 保存配置 密钥单独保存
+async fn save_config(global_data: &GlobalData, config: &OcrConfig) -> Result<()> {
+    global_data
+        .set_secret(API_KEY_SECRET.to_string(), &config.api_key)
+        .await?;
+    match &config.pdf_password {
+        Some(passwd) => {
+            global_data
+                .set_secret(PDF_PASSWORD_SECRET.to_string(), passwd)
+                .await?
+        }
+        None => global_data.del_secret(PDF_PASSWORD_SECRET.to_string()).await?,
+    }
+    global_data.set_data(CONFIG_CACHE.to_string(), config).await
+}
@@ -1,6 +1,118 @@
 use anyhow::Result;
 use async_trait::async_trait;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::mpsc::UnboundedSender;
+use crate::messages::common::ProgressMsg;
+
+/// 空消息类型名称
+pub const EMPTY_MSG: &str = "EmptyMsg";
+
+/// 服务描述 由各服务声明
+pub struct ServiceDescriptor {
+    /// 服务名称
+    pub name: &'static str,
+    /// 支持的方法
+    pub funcs: &'static [FuncDescriptor],
+    /// 支持的平台(同std::env::consts::OS)，为空时表示全平台
+    pub platforms: &'static [&'static str],
+    /// 默认超时时间，为空时不限制
+    pub timeout: Option<Duration>,
+}
+
+impl ServiceDescriptor {
+    /// 当前平台是否可用
+    pub fn is_available(&self) -> bool {
+        self.platforms.is_empty() || self.platforms.contains(&std::env::consts::OS)
+    }
+
+    /// 是否支持流式请求
+    pub fn is_stream(&self) -> bool {
+        self.funcs.iter().any(|f| f.is_stream)
+    }
+}
+
+/// 方法描述
+pub struct FuncDescriptor {
+    /// 方法名称
+    pub name: &'static str,
+    /// 请求消息类型
+    pub request: &'static str,
+    /// 响应消息类型
+    pub response: &'static str,
+    /// 是否为流式方法
+    pub is_stream: bool,
+    /// 是否为只读方法 只读方法交由服务的只读处理器并发执行
+    pub read_only: bool,
+    /// 超时设置 默认使用服务的超时时间
+    pub timeout: FuncTimeout,
+}
+
+/// 方法超时设置
+#[derive(Debug, Clone, Copy)]
+pub enum FuncTimeout {
+    /// 使用服务默认超时时间
+    Default,
+    /// 不限制 用于耗时取决于数据量的方法，如文件传输
+    Unlimited,
+    /// 指定超时时间
+    After(Duration),
+}
+
+impl FuncTimeout {
+    /// 实际超时时间 为空时不限制
+    pub fn resolve(self, default: Option<Duration>) -> Option<Duration> {
+        match self {
+            FuncTimeout::Default => default,
+            FuncTimeout::Unlimited => None,
+            FuncTimeout::After(timeout) => Some(timeout),
+        }
+    }
+}
+
+impl FuncDescriptor {
+    /// 普通方法
+    pub const fn new(name: &'static str, request: &'static str, response: &'static str) -> Self {
+        Self {
+            name,
+            request,
+            response,
+            is_stream: false,
+            read_only: false,
+            timeout: FuncTimeout::Default,
+        }
+    }
+
+    /// 只读方法
+    pub const fn read(name: &'static str, request: &'static str, response: &'static str) -> Self {
+        Self {
+            name,
+            request,
+            response,
+            is_stream: false,
+            read_only: true,
+            timeout: FuncTimeout::Default,
+        }
+    }
+
+    /// 流式方法
+    pub const fn stream(name: &'static str, request: &'static str, response: &'static str) -> Self {
+        Self {
+            name,
+            request,
+            response,
+            is_stream: true,
+            read_only: false,
+            timeout: FuncTimeout::Default,
+        }
+    }
+
+    /// 设置方法的超时时间
+    pub const fn timeout(mut self, timeout: FuncTimeout) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
 
 /// 服务
 #[async_trait]
@@ -11,6 +123,49 @@ pub trait Service: Send {
     async fn close(&mut self) -> Result<()> {
         Ok(())
     }
+    /// 只读处理器
+    /// 服务描述中标记为只读的方法交由其处理，不需要等待服务锁，可与耗时请求并发执行
+    fn read_handler(&self) -> Option<Arc<Box<dyn ImmService>>> {
+        None
+    }
+    /// 处理服务并上报进度
+    /// 需要上报进度的服务重写此方法，其余方法交由handle处理
+    async fn handle_progress(
+        &mut self,
+        func: &str,
+        req_data: Vec<u8>,
+        _progress: Progress,
+    ) -> Result<Option<Vec<u8>>> {
+        self.handle(func, req_data).await
+    }
+}
+
+/// 进度上报
+/// 由分发层转发为未结束的响应帧
+#[derive(Clone)]
+pub struct Progress {
+    tx: UnboundedSender<ProgressMsg>,
+}
+
+impl Progress {
+    pub fn new(tx: UnboundedSender<ProgressMsg>) -> Self {
+        Self { tx }
+    }
+
+    /// 不上报进度 用于无请求的后台任务
+    pub fn ignore() -> Self {
+        let (tx, _) = tokio::sync::mpsc::unbounded_channel();
+        Self { tx }
+    }
+
+    /// 上报进度 请求已结束时忽略
+    pub fn report(&self, current: u32, total: u32, label: impl Into<String>) {
+        let _ = self.tx.send(ProgressMsg {
+            current,
+            total,
+            label: label.into(),
+        });
+    }
 }
 
 /// 惰性初始化服务
@@ -188,14 +343,32 @@ mod macros {
         }
     }
 }
-    
+    // async fn func(&mut self, req: Req, progress: Progress) -> Result(Rsp);
+    #[macro_export]
+    macro_rules! async_progress_func_typetype {
+    ($self:ident, $function:ident, $data:ident, $($name:ident, $req:ty, $progress:ident),+) => {
+    match $function {
+        $(
+        stringify!($name) => {
+            let req = rinf::deserialize($data.as_slice())?;
+            let rsp = $self.$name(req, $progress).await?;
+            let buf = rinf::serialize(&rsp)?;
+            return Ok(Some(buf));
+        }
+        )*
+            _ =>{}
+        }
+    }
+}
+
     #[macro_export]
     macro_rules! func_end {
         ($function:ident) => {
-            Err(anyhow::Error::msg(format!(
+            Err($crate::common::error::ServiceError::InvalidInput(format!(
                 "没有function[{}]匹配",
                 $function
-            )))
+            ))
+            .into())
         };
     }
 }
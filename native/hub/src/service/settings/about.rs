@@ -1,6 +1,6 @@
 use crate::common::utils::{get_cache_dir, version};
 use crate::messages::common::StringMsg;
-use crate::service::service::{Service};
+use crate::service::service::{FuncDescriptor, Service, ServiceDescriptor, EMPTY_MSG};
 use std::time::Duration;
 
 use crate::{async_func_nono, async_func_notype, func_end, func_notype};
@@ -33,6 +33,19 @@ pub struct AboutService {
 const NAME: &str = "AboutService";
 const URL: &str = "https://nsfoxer-oss.oss-cn-beijing.aliyuncs.com/nftools/server.json";
 
+/// 服务描述
+pub const DESCRIPTOR: ServiceDescriptor = ServiceDescriptor {
+    name: NAME,
+    funcs: &[
+        FuncDescriptor::new("check_updates", EMPTY_MSG, "StringMsg"),
+        FuncDescriptor::new("record", EMPTY_MSG, "StringMsg"),
+        FuncDescriptor::new("version", EMPTY_MSG, "StringMsg"),
+        FuncDescriptor::new("install_newest", EMPTY_MSG, EMPTY_MSG),
+    ],
+    platforms: &[],
+    timeout: Some(Duration::from_secs(600)),
+};
+
 
 #[async_trait::async_trait]
 impl Service for AboutService {
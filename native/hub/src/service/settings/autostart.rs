@@ -1,15 +1,27 @@
 use crate::{common, func_end, func_notype, func_typeno};
 use crate::common::utils;
 use crate::messages::common::BoolMsg;
-use crate::service::service::{ImmService};
+use crate::service::service::{FuncDescriptor, ImmService, ServiceDescriptor, EMPTY_MSG};
 use anyhow::Result;
 use auto_launch::AutoLaunch;
+use std::time::Duration;
 
 /// 开机自启动服务
 pub struct AutoStartService {
     auto_launch: AutoLaunch,
 }
 
+/// 服务描述
+pub const DESCRIPTOR: ServiceDescriptor = ServiceDescriptor {
+    name: "AutoStartService",
+    funcs: &[
+        FuncDescriptor::new("get_autostart", EMPTY_MSG, "BoolMsg"),
+        FuncDescriptor::new("set_autostart", "BoolMsg", EMPTY_MSG),
+    ],
+    platforms: &[],
+    timeout: Some(Duration::from_secs(10)),
+};
+
 #[async_trait::async_trait]
 impl ImmService for AutoStartService {
     async fn handle(&self, func: &str, req_data: Vec<u8>) -> Result<Option<Vec<u8>>> {
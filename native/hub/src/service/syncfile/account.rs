@@ -0,0 +1,118 @@
+use crate::common::error::ServiceError;
+use crate::messages::common::{BoolMsg, StringMsg};
+use crate::messages::syncfile::SyncAccountMsg;
+use crate::service::syncfile::storage::{self, SyncStorage};
+use crate::service::syncfile::{AccountInfo, SyncFileService, SyncState};
+use anyhow::Result;
+use log::warn;
+
+/// 未指定账户时使用的账户 兼容单账户版本
+pub(super) const DEFAULT_ACCOUNT: &str = "default";
+
+/// 账户密码在系统密钥服务中的key
+pub(super) fn account_secret(name: &str) -> String {
+    format!("syncAccount.{}", name)
+}
+
+impl SyncState {
+    /// 文件夹所属账户 未绑定时取最近一次列出远端文件夹的结果
+    pub(super) fn account(&self, remote_dir: &str) -> &str {
+        self.file_sync
+            .accounts
+            .get(remote_dir)
+            .or_else(|| self.remote_accounts.get(remote_dir))
+            .map(|x| x.as_str())
+            .unwrap_or(DEFAULT_ACCOUNT)
+    }
+
+    /// 获取账户的远端存储
+    pub(super) fn account_storage(&self, account: &str) -> Result<&dyn SyncStorage> {
+        Ok(self
+            .storages
+            .get(account)
+            .map(|x| x.as_ref())
+            .ok_or_else(|| ServiceError::NotLoggedIn(format!("账户{}未连接，请登录", account)))?)
+    }
+
+    /// 获取文件夹所属账户的远端存储
+    pub(super) fn storage(&self, remote_dir: &str) -> Result<&dyn SyncStorage> {
+        self.account_storage(self.account(remote_dir))
+    }
+}
+
+impl SyncFileService {
+    /// 新增账户 连接成功后保存
+    pub(super) async fn add_account(&mut self, account: SyncAccountMsg) -> Result<()> {
+        if account.name.is_empty() {
+            return Err(ServiceError::InvalidInput("账户名称不能为空".to_string()).into());
+        }
+        if self.accounts.read().unwrap().contains_key(&account.name) {
+            return Err(ServiceError::InvalidInput(format!("账户{}已存在", account.name)).into());
+        }
+        self.save_account(account).await?;
+        Ok(())
+    }
+
+    /// 修改账户 重新连接
+    pub(super) async fn update_account(&mut self, account: SyncAccountMsg) -> Result<()> {
+        if !self.accounts.read().unwrap().contains_key(&account.name) {
+            return Err(ServiceError::NotFound(format!("账户{}不存在", account.name)).into());
+        }
+        self.save_account(account).await?;
+        Ok(())
+    }
+
+    /// 删除账户 存在绑定的同步文件夹时不允许删除
+    pub(super) async fn remove_account(&mut self, name: StringMsg) -> Result<()> {
+        let mut state = self.state.lock().await;
+        if state
+            .file_sync
+            .files
+            .keys()
+            .any(|x| state.account(x) == name.value)
+        {
+            return Err(ServiceError::InvalidInput(format!("账户{}下存在同步文件夹", name.value)).into());
+        }
+        state.storages.remove(&name.value);
+        self.accounts.write().unwrap().remove(&name.value);
+        self.global_data.del_secret(account_secret(&name.value)).await
+    }
+
+    /// 测试账户是否可以连接
+    pub(super) async fn test_account(&mut self, account: SyncAccountMsg) -> Result<BoolMsg> {
+        storage::connect(&AccountInfo::from(account)).await?;
+        Ok(BoolMsg { value: true })
+    }
+
+    /// 保存并连接账户 返回是否连接成功，连接失败不影响保存
+    pub(super) async fn save_account(&mut self, account: SyncAccountMsg) -> Result<bool> {
+        let name = account.name.clone();
+        let account = AccountInfo::from(account);
+        self.global_data.set_secret(account_secret(&name), &account.passwd).await?;
+        self.accounts.write().unwrap().insert(name.clone(), account.clone());
+        let mut state = self.state.lock().await;
+        match storage::connect(&account).await {
+            Ok(storage) => {
+                state.storages.insert(name, storage);
+                Ok(true)
+            }
+            Err(e) => {
+                warn!("账户{}连接失败: {}", name, e);
+                // 账户信息已变更 移除旧的连接
+                state.storages.remove(&name);
+                Ok(false)
+            }
+        }
+    }
+}
+
+impl From<SyncAccountMsg> for AccountInfo {
+    fn from(account: SyncAccountMsg) -> Self {
+        Self {
+            user: account.account,
+            passwd: account.passwd,
+            legacy_passwd: None,
+            url: account.url,
+        }
+    }
+}
@@ -0,0 +1,430 @@
+use crate::common::error::ServiceError;
+use crate::common::global_data::GlobalData;
+use crate::common::utils::sha256;
+use crate::common::WEBDAV_SYNC_DIR;
+use anyhow::Result;
+use bytes::Bytes;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::stream::{DecryptorBE32, EncryptorBE32};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use futures::Stream;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::borrow::Cow;
+use tokio::fs::File;
+use tokio::io::AsyncReadExt;
+
+// 密钥派生迭代次数
+const KDF_ROUNDS: u32 = 200_000;
+// 加密分块大小
+const CHUNK_SIZE: usize = 64 * 1024;
+// 每块的认证标签长度
+const TAG_SIZE: usize = 16;
+// 流加密的nonce前缀长度
+const STREAM_NONCE_SIZE: usize = 7;
+// 元数据加密的nonce长度
+const NONCE_SIZE: usize = 12;
+
+/// 远端属性文件中的加密信息 用于校验密码
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) struct CryptoInfo {
+    // 密钥派生盐 hex
+    salt: String,
+    // 密钥校验值 hex
+    verifier: String,
+    // 是否混淆文件名
+    names: bool,
+}
+
+/// 文件夹加密密钥 由密码派生
+/// 加密文件内容及远端属性文件，可选混淆远端文件名
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) struct SyncCipher {
+    // 密钥 hex 保存在系统密钥服务中，首次使用时读取
+    #[serde(skip)]
+    key: String,
+    // 旧版本明文保存的密钥 迁移到系统密钥服务后清空
+    #[serde(rename = "key", default, skip_serializing_if = "Option::is_none")]
+    legacy: Option<String>,
+    // 密钥派生盐 hex
+    salt: String,
+    // 是否混淆文件名
+    names: bool,
+}
+
+impl SyncCipher {
+    /// 新建加密 随机生成盐
+    pub(super) fn create(passphrase: &str, names: bool) -> Self {
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        Self::derive(passphrase, &salt, names)
+    }
+
+    /// 以远端加密信息校验密码 密码错误时返回错误
+    pub(super) fn open(passphrase: &str, info: &CryptoInfo) -> Result<Self> {
+        let salt = from_hex(&info.salt)?;
+        let cipher = Self::derive(passphrase, &salt, info.names);
+        cipher.verify(info)?;
+        Ok(cipher)
+    }
+
+    /// 校验密钥与远端加密信息是否一致
+    pub(super) fn verify(&self, info: &CryptoInfo) -> Result<()> {
+        self.check_key()?;
+        if self.verifier() != info.verifier {
+            return Err(ServiceError::InvalidInput("文件夹密码错误".to_string()).into());
+        }
+        Ok(())
+    }
+
+    fn derive(passphrase: &str, salt: &[u8], names: bool) -> Self {
+        let key = pbkdf2::pbkdf2_hmac_array::<Sha256, 32>(passphrase.as_bytes(), salt, KDF_ROUNDS);
+        Self {
+            key: to_hex(&key),
+            legacy: None,
+            salt: to_hex(salt),
+            names,
+        }
+    }
+
+    /// 密钥 hex
+    pub(super) fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// 从系统密钥服务读取密钥 旧版本明文保存的密钥一并迁移
+    /// 密钥丢失时保持为空，同步时提示需要密码
+    pub(super) async fn load_key(&mut self, global_data: &GlobalData, remote_dir: &str) {
+        match global_data.migrate_secret(cipher_secret(remote_dir), &mut self.legacy).await {
+            Some(key) => self.key = key,
+            None => warn!("文件夹{}的密钥丢失，需重新输入密码", remote_dir),
+        }
+    }
+
+    /// 密钥丢失时返回错误
+    fn check_key(&self) -> Result<()> {
+        if self.key.is_empty() {
+            return Err(ServiceError::InvalidInput("文件夹已加密，需要密码".to_string()).into());
+        }
+        Ok(())
+    }
+
+    /// 写入远端属性文件的加密信息
+    pub(super) fn info(&self) -> CryptoInfo {
+        CryptoInfo {
+            salt: self.salt.clone(),
+            verifier: self.verifier(),
+            names: self.names,
+        }
+    }
+
+    /// 密钥校验值 不可反推密钥
+    fn verifier(&self) -> String {
+        sha256(format!("nftools-sync-verify:{}", self.key).as_bytes())
+    }
+
+    fn aead(&self) -> Result<ChaCha20Poly1305> {
+        self.check_key()?;
+        let key = from_hex(&self.key)?;
+        Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+    }
+
+    /// 远端文件名 开启混淆时每级路径替换为hash
+    pub(super) fn name<'a>(&self, file: &'a str) -> Cow<'a, str> {
+        if !self.names {
+            return Cow::Borrowed(file);
+        }
+        let name = file
+            .split('/')
+            .map(|x| {
+                if x.is_empty() {
+                    String::new()
+                } else {
+                    sha256(format!("{}:{}", self.key, x).as_bytes())[..32].to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        Cow::Owned(name)
+    }
+
+    /// 加密数据 返回 nonce+密文 的hex
+    pub(super) fn seal(&self, data: &[u8]) -> Result<String> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let mut result = nonce.to_vec();
+        result.extend(self.aead()?.encrypt(&nonce, data).map_err(crypto_error)?);
+        Ok(to_hex(&result))
+    }
+
+    /// 解密seal加密的数据
+    pub(super) fn unseal(&self, data: &str) -> Result<Vec<u8>> {
+        let data = from_hex(data)?;
+        if data.len() < NONCE_SIZE {
+            return Err(crypto_error(()).into());
+        }
+        let (nonce, data) = data.split_at(NONCE_SIZE);
+        Ok(self
+            .aead()?
+            .decrypt(Nonce::from_slice(nonce), data)
+            .map_err(crypto_error)?)
+    }
+
+    /// 按块加密读取文件的流 首块前写入nonce前缀
+    pub(super) fn encrypt_stream(
+        &self,
+        file: File,
+    ) -> Result<impl Stream<Item = std::io::Result<Bytes>> + Send + 'static> {
+        let mut prefix = [0u8; STREAM_NONCE_SIZE];
+        OsRng.fill_bytes(&mut prefix);
+        let encryptor = EncryptorBE32::from_aead(self.aead()?, prefix.as_slice().into());
+        let state = (file, Some(encryptor), None::<Vec<u8>>, Some(prefix.to_vec()));
+        Ok(futures::stream::try_unfold(
+            state,
+            |(mut file, mut encryptor, pending, header)| async move {
+                if let Some(header) = header {
+                    return Ok(Some((Bytes::from(header), (file, encryptor, pending, None))));
+                }
+                let Some(enc) = encryptor.as_mut() else {
+                    return Ok(None);
+                };
+                // 预读下一块 判断当前块是否为最后一块
+                let current = match pending {
+                    Some(x) => x,
+                    None => read_chunk(&mut file).await?,
+                };
+                let next = read_chunk(&mut file).await?;
+                let data = if next.is_empty() {
+                    encryptor
+                        .take()
+                        .unwrap()
+                        .encrypt_last(current.as_slice())
+                        .map_err(io_error)?
+                } else {
+                    enc.encrypt_next(current.as_slice()).map_err(io_error)?
+                };
+                let pending = (!next.is_empty()).then_some(next);
+                Ok::<_, std::io::Error>(Some((Bytes::from(data), (file, encryptor, pending, None))))
+            },
+        ))
+    }
+
+    /// 流解密器
+    pub(super) fn decryptor(&self) -> Result<StreamDecryptor> {
+        Ok(StreamDecryptor {
+            aead: Some(self.aead()?),
+            decryptor: None,
+            buf: Vec::new(),
+        })
+    }
+}
+
+/// 按块解密下载的数据
+pub(super) struct StreamDecryptor {
+    aead: Option<ChaCha20Poly1305>,
+    decryptor: Option<DecryptorBE32<ChaCha20Poly1305>>,
+    buf: Vec<u8>,
+}
+
+impl StreamDecryptor {
+    /// 写入密文 返回已可解密的明文
+    pub(super) fn push(&mut self, data: &[u8]) -> Result<Vec<u8>> {
+        self.buf.extend_from_slice(data);
+        if self.decryptor.is_none() {
+            if self.buf.len() < STREAM_NONCE_SIZE {
+                return Ok(Vec::new());
+            }
+            let prefix: Vec<u8> = self.buf.drain(..STREAM_NONCE_SIZE).collect();
+            let aead = self.aead.take().unwrap();
+            self.decryptor = Some(DecryptorBE32::from_aead(aead, prefix.as_slice().into()));
+        }
+        let decryptor = self.decryptor.as_mut().unwrap();
+        let mut result = Vec::new();
+        // 保留最后一块 结束时以decrypt_last解密
+        while self.buf.len() > CHUNK_SIZE + TAG_SIZE {
+            let chunk: Vec<u8> = self.buf.drain(..CHUNK_SIZE + TAG_SIZE).collect();
+            result.extend(decryptor.decrypt_next(chunk.as_slice()).map_err(crypto_error)?);
+        }
+        Ok(result)
+    }
+
+    /// 结束 返回最后一块明文 数据不完整或被篡改时返回错误
+    pub(super) fn finish(self) -> Result<Vec<u8>> {
+        let decryptor = self.decryptor.ok_or_else(|| crypto_error(()))?;
+        Ok(decryptor
+            .decrypt_last(self.buf.as_slice())
+            .map_err(crypto_error)?)
+    }
+}
+
+/// 文件夹密钥在系统密钥服务中的key
+pub(super) fn cipher_secret(remote_dir: &str) -> String {
+    format!("syncFolderKey.{}", remote_dir)
+}
+
+/// 远端文件完整路径
+pub(super) fn remote_path(cipher: Option<&SyncCipher>, remote_dir: &str, file: &str) -> String {
+    match cipher {
+        Some(cipher) => format!("{WEBDAV_SYNC_DIR}{remote_dir}{}", cipher.name(file)),
+        None => format!("{WEBDAV_SYNC_DIR}{remote_dir}{file}"),
+    }
+}
+
+/// 读取一块数据 文件结束时为空
+async fn read_chunk(file: &mut File) -> std::io::Result<Vec<u8>> {
+    let mut buf = vec![0; CHUNK_SIZE];
+    let mut len = 0;
+    while len < CHUNK_SIZE {
+        let n = file.read(&mut buf[len..]).await?;
+        if n == 0 {
+            break;
+        }
+        len += n;
+    }
+    buf.truncate(len);
+    Ok(buf)
+}
+
+fn crypto_error<E>(_: E) -> ServiceError {
+    ServiceError::InvalidInput("解密失败，密码错误或数据已损坏".to_string())
+}
+
+fn io_error<E>(_: E) -> std::io::Error {
+    std::io::Error::other("加密失败")
+}
+
+fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|x| format!("{:02x}", x)).collect()
+}
+
+fn from_hex(data: &str) -> Result<Vec<u8>> {
+    (0..data.len())
+        .step_by(2)
+        .map(|i| {
+            data.get(i..i + 2)
+                .and_then(|x| u8::from_str_radix(x, 16).ok())
+                .ok_or_else(|| ServiceError::InvalidInput("无效的密钥数据".to_string()).into())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::TryStreamExt;
+
+    /// 加密文件后按指定大小分段解密
+    async fn round_trip(cipher: &SyncCipher, data: &[u8], piece: usize) -> (usize, Vec<u8>) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data");
+        tokio::fs::write(&path, data).await.unwrap();
+        let file = File::open(&path).await.unwrap();
+        let chunks: Vec<Bytes> = cipher.encrypt_stream(file).unwrap().try_collect().await.unwrap();
+        let encrypted = chunks.concat();
+        let mut decryptor = cipher.decryptor().unwrap();
+        let mut result = Vec::new();
+        for piece in encrypted.chunks(piece) {
+            result.extend(decryptor.push(piece).unwrap());
+        }
+        result.extend(decryptor.finish().unwrap());
+        (encrypted.len(), result)
+    }
+
+    #[tokio::test]
+    async fn stream_round_trip_at_chunk_boundaries() {
+        let cipher = SyncCipher::create("passphrase", false);
+        for len in [
+            0,
+            1,
+            CHUNK_SIZE - 1,
+            CHUNK_SIZE,
+            CHUNK_SIZE + 1,
+            2 * CHUNK_SIZE,
+            3 * CHUNK_SIZE + 5,
+        ] {
+            let data: Vec<u8> = (0..len).map(|x| (x % 251) as u8).collect();
+            for piece in [1000, CHUNK_SIZE + TAG_SIZE, 3 * CHUNK_SIZE] {
+                let (encrypted, result) = round_trip(&cipher, &data, piece).await;
+                // 空文件也有一块 仅含认证标签
+                let chunks = len.div_ceil(CHUNK_SIZE).max(1);
+                assert_eq!(encrypted, STREAM_NONCE_SIZE + len + chunks * TAG_SIZE, "len {}", len);
+                assert_eq!(result, data, "len {} piece {}", len, piece);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn stream_rejects_tampered_or_truncated_data() {
+        let cipher = SyncCipher::create("passphrase", false);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data");
+        tokio::fs::write(&path, vec![7u8; CHUNK_SIZE + 10]).await.unwrap();
+        let file = File::open(&path).await.unwrap();
+        let chunks: Vec<Bytes> = cipher.encrypt_stream(file).unwrap().try_collect().await.unwrap();
+        let encrypted = chunks.concat();
+
+        let mut tampered = encrypted.clone();
+        tampered[STREAM_NONCE_SIZE + 1] ^= 1;
+        let mut decryptor = cipher.decryptor().unwrap();
+        assert!(decryptor.push(&tampered).is_err());
+
+        let mut decryptor = cipher.decryptor().unwrap();
+        decryptor.push(&encrypted[..encrypted.len() - 1]).unwrap();
+        assert!(decryptor.finish().is_err());
+
+        // 截掉最后一块 剩余的块不能作为最后一块解密
+        let mut decryptor = cipher.decryptor().unwrap();
+        decryptor.push(&encrypted[..STREAM_NONCE_SIZE + CHUNK_SIZE + TAG_SIZE]).unwrap();
+        assert!(decryptor.finish().is_err());
+    }
+
+    #[test]
+    fn open_verifies_passphrase() {
+        let cipher = SyncCipher::create("passphrase", true);
+        let info = cipher.info();
+        let opened = SyncCipher::open("passphrase", &info).unwrap();
+        assert_eq!(opened.key(), cipher.key());
+        assert!(SyncCipher::open("wrong", &info).is_err());
+
+        let sealed = cipher.seal(b"metadata").unwrap();
+        assert_eq!(opened.unseal(&sealed).unwrap(), b"metadata");
+        let other = SyncCipher::create("passphrase", true);
+        assert!(other.unseal(&sealed).is_err());
+    }
+
+    #[test]
+    fn obfuscated_names_keep_structure() {
+        let mut cipher = SyncCipher::create("passphrase", false);
+        assert_eq!(cipher.name("/a/b.txt"), "/a/b.txt");
+        cipher.names = true;
+        let name = cipher.name("/a/b.txt");
+        let parts: Vec<&str> = name.split('/').collect();
+        assert_eq!(parts.len(), 3);
+        assert!(parts[0].is_empty() && parts[1].len() == 32 && parts[2].len() == 32);
+        // 同一文件夹下的文件共享上级文件夹的名称
+        assert!(cipher.name("/a/c.txt").starts_with(&format!("/{}/", parts[1])));
+        assert_eq!(cipher.name("/a/"), format!("/{}/", parts[1]));
+    }
+
+    #[test]
+    fn key_is_not_serialized() {
+        let cipher = SyncCipher::create("passphrase", false);
+        let json = serde_json::to_string(&cipher).unwrap();
+        assert!(!json.contains(cipher.key()));
+        let restored: SyncCipher = serde_json::from_str(&json).unwrap();
+        assert!(restored.key().is_empty());
+        // 密钥丢失时不能以空密钥加解密
+        assert!(restored.verify(&cipher.info()).is_err());
+        assert!(restored.seal(b"metadata").is_err());
+    }
+
+    #[test]
+    fn legacy_key_is_kept_until_migrated() {
+        let json = r#"{"key":"00ff","salt":"01","names":false}"#;
+        let cipher: SyncCipher = serde_json::from_str(json).unwrap();
+        assert_eq!(cipher.legacy.as_deref(), Some("00ff"));
+        let json = serde_json::to_string(&cipher).unwrap();
+        assert!(json.contains("00ff"));
+    }
+}
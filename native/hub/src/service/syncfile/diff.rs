@@ -0,0 +1,599 @@
+use crate::common::error::ServiceError;
+use crate::common::global_data::GlobalData;
+use crate::messages::syncfile::{
+    ConflictPolicyMsg, FileStatusEnumMsg, SyncActionEnumMsg, SyncPreviewItemMsg,
+};
+use crate::service::syncfile::hash::file_sha256;
+use crate::service::syncfile::ignore::IgnoreRules;
+use crate::service::syncfile::{LocalFileMetadata, RemoteFileMedata, SyncFileService, SyncState};
+use ahash::{AHashMap, AHashSet};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SYNC_BASELINE_PREFIX: &str = "syncBaseline";
+
+/// 文件同步基线 上次同步成功时的文件状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) struct FileBaseline {
+    // 修改时间 ms
+    pub(super) mtime: u128,
+    // 文件内容sha256 文件夹为空
+    pub(super) hash: Option<String>,
+}
+
+/// 文件夹同步基线 k: 文件路径
+pub(super) type SyncBaseline = AHashMap<String, FileBaseline>;
+
+/// 同步计划
+#[derive(Debug, Default)]
+pub(super) struct SyncPlan {
+    // 需要上传的文件
+    pub(super) upload: Vec<String>,
+    // 需要下载的文件
+    pub(super) download: Vec<String>,
+    // 需要删除的远端文件
+    pub(super) delete_remote: Vec<String>,
+    // 需要删除的本地文件
+    pub(super) delete_local: Vec<String>,
+    // 双方均有修改的文件
+    pub(super) conflicts: Vec<String>,
+    // 被忽略的文件
+    pub(super) ignored: Vec<String>,
+}
+
+impl SyncPlan {
+    /// 整体状态
+    pub(super) fn status(&self) -> FileStatusEnumMsg {
+        if !self.conflicts.is_empty() {
+            return FileStatusEnumMsg::CONFLICT;
+        }
+        let upload = !self.upload.is_empty() || !self.delete_remote.is_empty();
+        let download = !self.download.is_empty() || !self.delete_local.is_empty();
+        match (upload, download) {
+            (true, true) => FileStatusEnumMsg::MIXED,
+            (true, false) => FileStatusEnumMsg::UPLOAD,
+            (false, true) => FileStatusEnumMsg::DOWNLOAD,
+            (false, false) => FileStatusEnumMsg::SYNCED,
+        }
+    }
+
+    /// 需要执行的操作数量
+    pub(super) fn len(&self) -> usize {
+        self.upload.len() + self.download.len() + self.delete_remote.len() + self.delete_local.len()
+    }
+
+    /// 按 (新增，删除，修改) 归类文件
+    pub(super) fn classify(
+        &self,
+        l_metadata: &LocalFileMetadata,
+        r_metadata: &RemoteFileMedata,
+    ) -> (Vec<String>, Vec<String>, Vec<String>) {
+        let mut add_files = Vec::new();
+        let mut modify_files = Vec::new();
+        for file in &self.upload {
+            if r_metadata.files.contains_key(file) {
+                modify_files.push(file.clone());
+            } else {
+                add_files.push(file.clone());
+            }
+        }
+        for file in &self.download {
+            if l_metadata.files.contains_key(file) {
+                modify_files.push(file.clone());
+            } else {
+                add_files.push(file.clone());
+            }
+        }
+        let del_files = self
+            .delete_remote
+            .iter()
+            .chain(self.delete_local.iter())
+            .cloned()
+            .collect();
+        (add_files, del_files, modify_files)
+    }
+
+    /// 移除不执行的文件
+    pub(super) fn exclude(&mut self, exclude: &[String]) {
+        if exclude.is_empty() {
+            return;
+        }
+        for files in [
+            &mut self.upload,
+            &mut self.download,
+            &mut self.delete_remote,
+            &mut self.delete_local,
+            &mut self.conflicts,
+        ] {
+            files.retain(|x| !exclude.contains(x));
+        }
+    }
+
+    /// 每个文件的操作及大小
+    pub(super) fn preview(
+        &self,
+        l_metadata: &LocalFileMetadata,
+        r_metadata: &RemoteFileMedata,
+    ) -> Vec<SyncPreviewItemMsg> {
+        let actions = [
+            (&self.upload, SyncActionEnumMsg::UPLOAD),
+            (&self.download, SyncActionEnumMsg::DOWNLOAD),
+            (&self.delete_local, SyncActionEnumMsg::DELETE_LOCAL),
+            (&self.delete_remote, SyncActionEnumMsg::DELETE_REMOTE),
+            (&self.conflicts, SyncActionEnumMsg::CONFLICT),
+        ];
+        let mut items = Vec::with_capacity(self.len() + self.conflicts.len());
+        for (files, action) in actions {
+            for file in files {
+                items.push(SyncPreviewItemMsg {
+                    file: file.clone(),
+                    action: action.clone(),
+                    local_size: l_metadata.sizes.get(file).copied().unwrap_or_default(),
+                    remote_size: r_metadata.sizes.get(file).copied().unwrap_or_default(),
+                });
+            }
+        }
+        items.sort_by(|a, b| a.file.cmp(&b.file));
+        items
+    }
+
+    /// 整理删除操作
+    /// 仍有文件需要保留(含被忽略的文件)的文件夹及部分选择同步的文件夹不删除
+    /// 已删除文件夹下的文件不再单独删除
+    fn prune_deletes(&mut self, ignore: &IgnoreRules) {
+        let kept: Vec<&String> = self
+            .upload
+            .iter()
+            .chain(self.download.iter())
+            .chain(self.conflicts.iter())
+            .chain(self.ignored.iter())
+            .collect();
+        for deletes in [&mut self.delete_remote, &mut self.delete_local] {
+            deletes.retain(|x| !x.ends_with('/') || !kept.iter().any(|k| k.starts_with(x.as_str())));
+            deletes.retain(|x| !ignore.is_partial(x));
+            deletes.sort();
+            let mut dirs: Vec<String> = Vec::new();
+            deletes.retain(|x| {
+                if dirs.iter().any(|d| x.starts_with(d.as_str())) {
+                    return false;
+                }
+                if x.ends_with('/') {
+                    dirs.push(x.clone());
+                }
+                true
+            });
+        }
+    }
+}
+
+/// 文件夹当前的对比结果
+pub(super) struct FolderDiff<'a> {
+    pub(super) local_dir: &'a String,
+    pub(super) l_metadata: LocalFileMetadata,
+    pub(super) r_metadata: RemoteFileMedata,
+    pub(super) baseline: Option<SyncBaseline>,
+    pub(super) plan: SyncPlan,
+    pub(super) ignore: IgnoreRules,
+}
+
+impl SyncFileService {
+    /// 读取本地、远端文件属性及基线 生成同步计划
+    pub(super) async fn diff_folder<'a>(
+        global_data: &GlobalData,
+        state: &'a SyncState,
+        remote_dir: &str,
+    ) -> Result<FolderDiff<'a>> {
+        // 获取本地文件属性
+        let local_dir = state
+            .file_sync
+            .files
+            .get(remote_dir)
+            .ok_or(ServiceError::NotFound("远端路径不存在".to_string()))?;
+        let ignore = state.ignore_rules(remote_dir, local_dir).await;
+        let l_metadata = state.local_metadata(local_dir, &ignore).await?;
+        // 获取远端文件属性
+        let r_metadata = state.remote_metadata(remote_dir).await?;
+        // 与上次同步的基线对比文件差异
+        let baseline = Self::load_baseline(global_data, remote_dir).await;
+        let plan = Self::diff_local_remote_file(&l_metadata, &r_metadata, baseline.as_ref(), &ignore);
+        Ok(FolderDiff {
+            local_dir,
+            l_metadata,
+            r_metadata,
+            baseline,
+            plan,
+            ignore,
+        })
+    }
+
+    /// 对比本地与远端文件差异
+    /// 有基线时与基线三方对比，仅一方修改的文件同步到另一方，双方均修改的文件标记为冲突
+    /// 无基线时(首次同步)不删除任何文件，双方均存在且内容不同的文件以修改时间较新的为准
+    /// 双方均有hash时以内容判断是否修改，否则以修改时间判断
+    /// 被忽略的文件不做任何操作
+    pub(super) fn diff_local_remote_file(
+        l_metadata: &LocalFileMetadata,
+        r_metadata: &RemoteFileMedata,
+        baseline: Option<&SyncBaseline>,
+        ignore: &IgnoreRules,
+    ) -> SyncPlan {
+        let mut plan = SyncPlan {
+            ignored: l_metadata.ignored.clone(),
+            ..Default::default()
+        };
+        let files: AHashSet<&String> = l_metadata
+            .files
+            .keys()
+            .chain(r_metadata.files.keys())
+            .collect();
+
+        for file in files {
+            if ignore.is_ignored(file) {
+                // 本地已忽略的文件夹下的远端文件不重复计数 未选择同步的文件不计数
+                if !ignore.is_unselected(file)
+                    && !l_metadata.ignored.iter().any(|x| file.starts_with(x.as_str()))
+                {
+                    plan.ignored.push(file.clone());
+                }
+                continue;
+            }
+            let local = l_metadata.files.get(file);
+            let remote = r_metadata.files.get(file);
+            let l_hash = l_metadata.hashes.get(file);
+            let r_hash = r_metadata.hashes.get(file);
+            // 双方内容是否一致
+            let same = |l: &u128, r: &u128| match (l_hash, r_hash) {
+                (Some(lh), Some(rh)) => lh == rh,
+                _ => l == r,
+            };
+            let Some(baseline) = baseline else {
+                match (local, remote) {
+                    (Some(_), None) => plan.upload.push(file.clone()),
+                    (None, Some(_)) => plan.download.push(file.clone()),
+                    (Some(l), Some(r)) if !file.ends_with('/') && !same(l, r) => {
+                        if l > r {
+                            plan.upload.push(file.clone());
+                        } else {
+                            plan.download.push(file.clone());
+                        }
+                    }
+                    _ => {}
+                }
+                continue;
+            };
+
+            // 文件夹只对比是否存在
+            let base = baseline.get(file);
+            let changed = |time: Option<&u128>, hash: Option<&String>| match (time, base) {
+                (Some(t), Some(b)) => {
+                    !file.ends_with('/')
+                        && match (hash, &b.hash) {
+                            (Some(h), Some(bh)) => h != bh,
+                            _ => *t != b.mtime,
+                        }
+                }
+                (None, None) => false,
+                _ => true,
+            };
+            match (changed(local, l_hash), changed(remote, r_hash)) {
+                (false, false) => {}
+                (true, false) => match local {
+                    Some(_) => plan.upload.push(file.clone()),
+                    None => plan.delete_remote.push(file.clone()),
+                },
+                (false, true) => match remote {
+                    Some(_) => plan.download.push(file.clone()),
+                    None => plan.delete_local.push(file.clone()),
+                },
+                (true, true) => match (local, remote) {
+                    // 双方均已删除
+                    (None, None) => {}
+                    // 双方修改为相同内容，视为已同步
+                    (Some(l), Some(r)) if file.ends_with('/') || same(l, r) => {}
+                    _ => plan.conflicts.push(file.clone()),
+                },
+            }
+        }
+        plan.prune_deletes(ignore);
+        plan
+    }
+
+    /// 按策略处理冲突 转换为上传、下载或删除操作
+    pub(super) async fn resolve_conflicts(
+        local_dir: &str,
+        policy: ConflictPolicyMsg,
+        l_metadata: &LocalFileMetadata,
+        r_metadata: &RemoteFileMedata,
+        plan: &mut SyncPlan,
+        ignore: &IgnoreRules,
+    ) -> Result<()> {
+        for file in plan.conflicts.clone() {
+            let local = l_metadata.files.contains_key(&file);
+            let remote = r_metadata.files.contains_key(&file);
+            match (policy, local, remote) {
+                (ConflictPolicyMsg::PreferLocal, true, _) => plan.upload.push(file),
+                (ConflictPolicyMsg::PreferLocal, false, _) => plan.delete_remote.push(file),
+                (ConflictPolicyMsg::PreferRemote, _, true) => plan.download.push(file),
+                (ConflictPolicyMsg::PreferRemote, _, false) => plan.delete_local.push(file),
+                // 双方均存在，本地文件重命名为冲突副本后上传，远端文件下载到原路径
+                (ConflictPolicyMsg::KeepBoth, true, true) => {
+                    let copy = conflict_copy_path(&file);
+                    tokio::fs::rename(local_path(local_dir, &file), local_path(local_dir, &copy))
+                        .await?;
+                    plan.upload.push(copy);
+                    plan.download.push(file);
+                }
+                // 一方删除一方修改，保留修改
+                (ConflictPolicyMsg::KeepBoth, true, false) => plan.upload.push(file),
+                (ConflictPolicyMsg::KeepBoth, false, _) => plan.download.push(file),
+            }
+        }
+        plan.prune_deletes(ignore);
+        Ok(())
+    }
+
+    /// 读取文件夹同步基线 未同步过时为空
+    pub(super) async fn load_baseline(global_data: &GlobalData, remote_dir: &str) -> Option<SyncBaseline> {
+        global_data
+            .get_data(format!("{}-{}", SYNC_BASELINE_PREFIX, remote_dir))
+            .await
+    }
+
+    /// 同步完成后以远端属性记录新的基线
+    /// 未执行的文件保留原基线，下次同步时仍会处理
+    /// 未选择同步的文件不记录，之后选择时按远端新增下载
+    pub(super) async fn save_baseline(
+        global_data: &GlobalData,
+        local_dir: &str,
+        remote_dir: &str,
+        r_metadata: &RemoteFileMedata,
+        old: Option<SyncBaseline>,
+        exclude: &[String],
+        ignore: &IgnoreRules,
+    ) -> Result<()> {
+        let old = old.unwrap_or_default();
+        let mut baseline = SyncBaseline::with_capacity(r_metadata.files.len());
+        for file in exclude {
+            if let Some(base) = old.get(file) {
+                baseline.insert(file.clone(), base.clone());
+            }
+        }
+        for (file, mtime) in &r_metadata.files {
+            if exclude.contains(file) || ignore.is_unselected(file) {
+                continue;
+            }
+            let hash = if file.ends_with('/') {
+                None
+            } else if let Some(hash) = r_metadata.hashes.get(file) {
+                Some(hash.clone())
+            } else {
+                match old.get(file) {
+                    Some(b) if b.mtime == *mtime && b.hash.is_some() => b.hash.clone(),
+                    _ => Self::local_file_hash(local_dir, file).await.ok(),
+                }
+            };
+            baseline.insert(file.clone(), FileBaseline { mtime: *mtime, hash });
+        }
+        global_data
+            .set_data(format!("{}-{}", SYNC_BASELINE_PREFIX, remote_dir), &baseline)
+            .await
+    }
+
+    /// 删除文件夹同步基线
+    pub(super) async fn del_baseline(global_data: &GlobalData, remote_dir: &str) -> Result<()> {
+        global_data
+            .del_data(format!("{}-{}", SYNC_BASELINE_PREFIX, remote_dir))
+            .await
+    }
+
+    /// 计算本地文件sha256
+    async fn local_file_hash(local_dir: &str, file: &str) -> Result<String> {
+        file_sha256(&local_path(local_dir, file)).await
+    }
+}
+
+/// 同步路径对应的本地路径
+pub(super) fn local_path(local_dir: &str, file: &str) -> PathBuf {
+    let mut path = PathBuf::from(local_dir);
+    for p in file.split('/') {
+        if !p.is_empty() {
+            path.push(p);
+        }
+    }
+    path
+}
+
+/// 冲突副本路径 如 /a/b.txt -> /a/b.conflict-时间戳.txt
+fn conflict_copy_path(file: &str) -> String {
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_millis())
+        .unwrap_or_default();
+    let (dir, name) = file.rsplit_once('/').unwrap_or(("", file));
+    match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => format!("{dir}/{stem}.conflict-{time}.{ext}"),
+        _ => format!("{dir}/{name}.conflict-{time}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 构造文件属性 hash为空时不记录
+    fn metadata(files: &[(&str, u128, &str)]) -> RemoteFileMedata {
+        let mut metadata = RemoteFileMedata::default();
+        for (file, mtime, hash) in files {
+            metadata.files.insert(file.to_string(), *mtime);
+            if !hash.is_empty() {
+                metadata.hashes.insert(file.to_string(), hash.to_string());
+            }
+        }
+        metadata
+    }
+
+    fn baseline(files: &[(&str, u128, &str)]) -> SyncBaseline {
+        files
+            .iter()
+            .map(|(file, mtime, hash)| {
+                let hash = (!hash.is_empty()).then(|| hash.to_string());
+                (file.to_string(), FileBaseline { mtime: *mtime, hash })
+            })
+            .collect()
+    }
+
+    fn conflict_plan(files: &[&str]) -> SyncPlan {
+        SyncPlan {
+            conflicts: files.iter().map(|x| x.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn sorted(files: &[String]) -> Vec<&str> {
+        let mut files: Vec<&str> = files.iter().map(|x| x.as_str()).collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn first_sync_without_baseline() {
+        let local = metadata(&[("/a.txt", 1, "a"), ("/same.txt", 1, "s"), ("/new.txt", 5, "n1")]);
+        let remote = metadata(&[("/b.txt", 1, "b"), ("/same.txt", 9, "s"), ("/new.txt", 3, "n2")]);
+        let plan = SyncFileService::diff_local_remote_file(&local, &remote, None, &IgnoreRules::default());
+        assert_eq!(sorted(&plan.upload), ["/a.txt", "/new.txt"]);
+        assert_eq!(sorted(&plan.download), ["/b.txt"]);
+        assert!(plan.delete_local.is_empty() && plan.delete_remote.is_empty());
+        assert!(plan.conflicts.is_empty());
+    }
+
+    #[test]
+    fn three_way_diff() {
+        let base = baseline(&[
+            ("/up.txt", 1, "1"),
+            ("/down.txt", 1, "1"),
+            ("/del_remote.txt", 1, "1"),
+            ("/del_local.txt", 1, "1"),
+            ("/conflict.txt", 1, "1"),
+            ("/same.txt", 1, "1"),
+            ("/touched.txt", 1, "1"),
+        ]);
+        let local = metadata(&[
+            ("/up.txt", 2, "2"),
+            ("/down.txt", 1, "1"),
+            ("/del_local.txt", 1, "1"),
+            ("/conflict.txt", 2, "l"),
+            ("/same.txt", 2, "2"),
+            ("/touched.txt", 5, "1"),
+        ]);
+        let remote = metadata(&[
+            ("/up.txt", 1, "1"),
+            ("/down.txt", 2, "2"),
+            ("/del_remote.txt", 1, "1"),
+            ("/conflict.txt", 3, "r"),
+            ("/same.txt", 3, "2"),
+            ("/touched.txt", 1, "1"),
+        ]);
+        let plan =
+            SyncFileService::diff_local_remote_file(&local, &remote, Some(&base), &IgnoreRules::default());
+        assert_eq!(sorted(&plan.upload), ["/up.txt"]);
+        assert_eq!(sorted(&plan.download), ["/down.txt"]);
+        assert_eq!(sorted(&plan.delete_remote), ["/del_remote.txt"]);
+        assert_eq!(sorted(&plan.delete_local), ["/del_local.txt"]);
+        assert_eq!(sorted(&plan.conflicts), ["/conflict.txt"]);
+        assert!(matches!(plan.status(), FileStatusEnumMsg::CONFLICT));
+    }
+
+    #[test]
+    fn ignored_files_are_skipped() {
+        let local = metadata(&[("/a.log", 1, "a"), ("/a.txt", 1, "a")]);
+        let remote = metadata(&[("/b.log", 1, "b")]);
+        let ignore = IgnoreRules::new(&["*.log"]);
+        let plan = SyncFileService::diff_local_remote_file(&local, &remote, None, &ignore);
+        assert_eq!(sorted(&plan.upload), ["/a.txt"]);
+        assert!(plan.download.is_empty());
+        assert_eq!(sorted(&plan.ignored), ["/a.log", "/b.log"]);
+    }
+
+    #[test]
+    fn deleted_dir_removes_children_once() {
+        let base = baseline(&[("/x/", 1, ""), ("/x/a.txt", 1, "a"), ("/y/", 1, ""), ("/y/a.txt", 1, "a")]);
+        let local = metadata(&[]);
+        // 远端在/y/下新增了文件 /y/不能删除
+        let remote = metadata(&[
+            ("/x/", 1, ""),
+            ("/x/a.txt", 1, "a"),
+            ("/y/", 1, ""),
+            ("/y/a.txt", 1, "a"),
+            ("/y/b.txt", 2, "b"),
+        ]);
+        let plan =
+            SyncFileService::diff_local_remote_file(&local, &remote, Some(&base), &IgnoreRules::default());
+        assert_eq!(sorted(&plan.delete_remote), ["/x/", "/y/a.txt"]);
+        assert_eq!(sorted(&plan.download), ["/y/b.txt"]);
+        assert!(matches!(plan.status(), FileStatusEnumMsg::MIXED));
+    }
+
+    #[tokio::test]
+    async fn resolve_conflicts_by_policy() {
+        let local = metadata(&[("/a.txt", 2, "l")]);
+        let remote = metadata(&[("/b.txt", 2, "r")]);
+        let ignore = IgnoreRules::default();
+
+        let mut plan = conflict_plan(&["/a.txt", "/b.txt"]);
+        SyncFileService::resolve_conflicts("", ConflictPolicyMsg::PreferLocal, &local, &remote, &mut plan, &ignore)
+            .await
+            .unwrap();
+        assert_eq!(sorted(&plan.upload), ["/a.txt"]);
+        assert_eq!(sorted(&plan.delete_remote), ["/b.txt"]);
+
+        let mut plan = conflict_plan(&["/a.txt", "/b.txt"]);
+        SyncFileService::resolve_conflicts("", ConflictPolicyMsg::PreferRemote, &local, &remote, &mut plan, &ignore)
+            .await
+            .unwrap();
+        assert_eq!(sorted(&plan.download), ["/b.txt"]);
+        assert_eq!(sorted(&plan.delete_local), ["/a.txt"]);
+
+        // 一方删除一方修改时保留修改
+        let mut plan = conflict_plan(&["/a.txt", "/b.txt"]);
+        SyncFileService::resolve_conflicts("", ConflictPolicyMsg::KeepBoth, &local, &remote, &mut plan, &ignore)
+            .await
+            .unwrap();
+        assert_eq!(sorted(&plan.upload), ["/a.txt"]);
+        assert_eq!(sorted(&plan.download), ["/b.txt"]);
+        assert!(plan.delete_local.is_empty() && plan.delete_remote.is_empty());
+    }
+
+    #[tokio::test]
+    async fn keep_both_renames_local_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let local_dir = dir.path().to_string_lossy().to_string();
+        tokio::fs::write(local_path(&local_dir, "/a.txt"), b"local").await.unwrap();
+        let local = metadata(&[("/a.txt", 2, "l")]);
+        let remote = metadata(&[("/a.txt", 3, "r")]);
+        let mut plan = conflict_plan(&["/a.txt"]);
+        SyncFileService::resolve_conflicts(
+            &local_dir,
+            ConflictPolicyMsg::KeepBoth,
+            &local,
+            &remote,
+            &mut plan,
+            &IgnoreRules::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(plan.download, ["/a.txt"]);
+        let copy = &plan.upload[0];
+        assert!(copy.starts_with("/a.conflict-") && copy.ends_with(".txt"));
+        assert!(!local_path(&local_dir, "/a.txt").exists());
+        assert_eq!(tokio::fs::read(local_path(&local_dir, copy)).await.unwrap(), b"local");
+    }
+
+    #[test]
+    fn conflict_copy_keeps_extension() {
+        assert!(conflict_copy_path("/a/b.txt").starts_with("/a/b.conflict-"));
+        assert!(conflict_copy_path("/a/b.txt").ends_with(".txt"));
+        assert!(conflict_copy_path("/a/.env").starts_with("/a/.env.conflict-"));
+    }
+}
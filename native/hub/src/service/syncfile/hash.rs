@@ -0,0 +1,131 @@
+use crate::common::global_data::GlobalData;
+use ahash::{AHashMap, AHashSet};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::sync::Mutex;
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, BufReader};
+
+const HASH_CACHE: &str = "syncHashCache";
+// 计算hash时每次读取的大小
+const HASH_BUF_SIZE: usize = 64 * 1024;
+
+/// 缓存的文件hash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct HashEntry {
+    // 文件大小
+    size: u64,
+    // 修改时间 ms
+    mtime: u128,
+    // 文件内容sha256
+    hash: String,
+}
+
+/// 本地文件hash缓存
+/// 以(路径，大小，修改时间)判断内容是否变化，未变化时不重新计算
+#[derive(Debug, Default)]
+pub(super) struct HashCache {
+    // k: 文件绝对路径
+    entries: Mutex<AHashMap<String, HashEntry>>,
+}
+
+impl HashCache {
+    /// 读取缓存
+    pub(super) async fn load(global_data: &GlobalData) -> Self {
+        let entries = global_data
+            .get_data(HASH_CACHE.to_string())
+            .await
+            .unwrap_or_default();
+        Self {
+            entries: Mutex::new(entries),
+        }
+    }
+
+    /// 保存缓存
+    pub(super) async fn save(&self, global_data: &GlobalData) -> Result<()> {
+        let entries = self.entries.lock().unwrap().clone();
+        global_data.set_data(HASH_CACHE.to_string(), &entries).await
+    }
+
+    /// 获取文件hash 缓存失效时重新计算
+    pub(super) async fn hash(&self, path: &Path, size: u64, mtime: u128) -> Result<String> {
+        let key = path.to_string_lossy().to_string();
+        if let Some(entry) = self.entries.lock().unwrap().get(&key)
+            && entry.size == size
+            && entry.mtime == mtime
+        {
+            return Ok(entry.hash.clone());
+        }
+        let hash = file_sha256(path).await?;
+        self.insert(key, size, mtime, hash.clone());
+        Ok(hash)
+    }
+
+    /// 记录已知内容的文件hash
+    pub(super) fn insert(&self, key: String, size: u64, mtime: u128, hash: String) {
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key, HashEntry { size, mtime, hash });
+    }
+
+    /// 移除目录下已不存在的文件
+    pub(super) fn prune(&self, dir: &str, exists: &AHashSet<String>) {
+        self.entries
+            .lock()
+            .unwrap()
+            .retain(|k, _| !Path::new(k).starts_with(dir) || exists.contains(k));
+    }
+}
+
+/// 分块读取计算文件sha256 不将整个文件读入内存
+pub(super) async fn file_sha256(path: &Path) -> Result<String> {
+    let mut reader = BufReader::with_capacity(HASH_BUF_SIZE, File::open(path).await?);
+    let mut buf = vec![0; HASH_BUF_SIZE];
+    let mut sha256 = Sha256::new();
+    loop {
+        let n = reader.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        sha256.update(&buf[..n]);
+    }
+    Ok(format!("{:x}", sha256.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::utils::sha256;
+
+    #[tokio::test]
+    async fn streamed_hash_matches_whole_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data");
+        let data: Vec<u8> = (0..3 * HASH_BUF_SIZE + 7).map(|x| (x % 251) as u8).collect();
+        tokio::fs::write(&path, &data).await.unwrap();
+        assert_eq!(file_sha256(&path).await.unwrap(), sha256(&data));
+
+        // 大小与修改时间未变化时使用缓存
+        let cache = HashCache::default();
+        assert_eq!(cache.hash(&path, 1, 1).await.unwrap(), sha256(&data));
+        tokio::fs::write(&path, b"changed").await.unwrap();
+        assert_eq!(cache.hash(&path, 1, 1).await.unwrap(), sha256(&data));
+        assert_eq!(cache.hash(&path, 7, 2).await.unwrap(), sha256(b"changed"));
+    }
+
+    #[test]
+    fn prune_only_matches_whole_components() {
+        let cache = HashCache::default();
+        for key in ["/data/a/x", "/data/a/y", "/data/ab/x"] {
+            cache.insert(key.to_string(), 1, 1, String::new());
+        }
+        let exists = AHashSet::from_iter(["/data/a/x".to_string()]);
+        cache.prune("/data/a", &exists);
+        let mut keys: Vec<String> = cache.entries.lock().unwrap().keys().cloned().collect();
+        keys.sort();
+        assert_eq!(keys, ["/data/a/x", "/data/ab/x"]);
+    }
+}
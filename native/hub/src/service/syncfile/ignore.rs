@@ -0,0 +1,294 @@
+use log::warn;
+use regex::Regex;
+use std::path::Path;
+
+/// 文件夹内的忽略规则文件
+pub(super) const IGNORE_FILE: &str = ".nfignore";
+
+/// 单条忽略规则
+#[derive(Debug)]
+struct IgnoreRule {
+    regex: Regex,
+    // 以!开头 重新包含
+    negate: bool,
+    // 以/结尾 仅匹配文件夹
+    dir_only: bool,
+}
+
+/// 忽略规则 gitignore语法
+/// 后出现的规则优先，被忽略的文件夹下所有内容均被忽略
+/// 设定了选择同步的子文件夹时，其外的路径同样视为忽略
+#[derive(Debug, Default)]
+pub(super) struct IgnoreRules {
+    rules: Vec<IgnoreRule>,
+    // 选择同步的子文件夹 以/开头和结尾，为空时同步全部
+    includes: Vec<String>,
+}
+
+impl IgnoreRules {
+    /// 由规则行构造 无效的规则跳过
+    pub(super) fn new<S: AsRef<str>>(patterns: &[S]) -> Self {
+        let rules = patterns
+            .iter()
+            .filter_map(|x| {
+                let rule = parse_rule(x.as_ref());
+                if let Some(Err(e)) = &rule {
+                    warn!("忽略规则{}无效: {}", x.as_ref(), e);
+                }
+                rule.and_then(|x| x.ok())
+            })
+            .collect();
+        Self {
+            rules,
+            includes: Vec::new(),
+        }
+    }
+
+    /// 读取本地文件夹下的.nfignore 并追加设定的规则及选择同步的子文件夹
+    pub(super) async fn load(
+        local_dir: &str,
+        patterns: Option<&Vec<String>>,
+        includes: Option<&Vec<String>>,
+    ) -> Self {
+        let content = tokio::fs::read_to_string(Path::new(local_dir).join(IGNORE_FILE))
+            .await
+            .unwrap_or_default();
+        let mut lines: Vec<&str> = content.lines().collect();
+        if let Some(patterns) = patterns {
+            lines.extend(patterns.iter().map(|x| x.as_str()));
+        }
+        let mut rules = Self::new(&lines);
+        rules.includes = includes.cloned().unwrap_or_default();
+        rules
+    }
+
+    /// 同步路径是否被忽略 文件夹以/结尾
+    pub(super) fn is_ignored(&self, path: &str) -> bool {
+        if self.is_unselected(path) {
+            return true;
+        }
+        if self.rules.is_empty() {
+            return false;
+        }
+        let is_dir = path.ends_with('/');
+        let path = path.trim_matches('/');
+        if path.is_empty() {
+            return false;
+        }
+        // 上级文件夹被忽略时 其下内容均被忽略
+        let mut end = 0;
+        while let Some(i) = path[end..].find('/') {
+            end += i;
+            if self.matched(&path[..end], true) {
+                return true;
+            }
+            end += 1;
+        }
+        self.matched(path, is_dir)
+    }
+
+    /// 同步路径是否在选择同步的子文件夹外 子文件夹的上级文件夹不算在外
+    pub(super) fn is_unselected(&self, path: &str) -> bool {
+        if self.includes.is_empty() || path.trim_matches('/').is_empty() {
+            return false;
+        }
+        !self
+            .includes
+            .iter()
+            .any(|x| path.starts_with(x.as_str()) || (path.ends_with('/') && x.starts_with(path)))
+    }
+
+    /// 文件夹下是否有未选择同步的内容 这样的文件夹不能整体删除
+    pub(super) fn is_partial(&self, dir: &str) -> bool {
+        dir.ends_with('/')
+            && self
+                .includes
+                .iter()
+                .any(|x| x.len() > dir.len() && x.starts_with(dir))
+    }
+
+    /// 按最后一条匹配的规则判断
+    fn matched(&self, path: &str, is_dir: bool) -> bool {
+        self.rules
+            .iter()
+            .rev()
+            .find(|x| (is_dir || !x.dir_only) && x.regex.is_match(path))
+            .is_some_and(|x| !x.negate)
+    }
+}
+
+/// 规范化选择同步的子文件夹 以/开头和结尾，空路径返回None
+pub(super) fn normalize_include(path: &str) -> Option<String> {
+    let path = path.trim().replace('\\', "/");
+    let path = path.trim_matches('/');
+    (!path.is_empty()).then(|| format!("/{}/", path))
+}
+
+/// 解析一行规则 空行及注释返回None
+fn parse_rule(line: &str) -> Option<Result<IgnoreRule, regex::Error>> {
+    let line = line.trim_end();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let (negate, line) = match line.strip_prefix('!') {
+        Some(x) => (true, x),
+        None => (false, line.strip_prefix('\\').unwrap_or(line)),
+    };
+    let (dir_only, line) = match line.strip_suffix('/') {
+        Some(x) => (true, x),
+        None => (false, line),
+    };
+    if line.is_empty() {
+        return None;
+    }
+    // 包含/时相对于同步根目录，否则匹配任意层级
+    let anchored = line.contains('/');
+    let line = line.trim_start_matches('/');
+    let mut regex = String::from("^");
+    if !anchored {
+        regex.push_str("(?:.*/)?");
+    }
+    regex.push_str(&glob_to_regex(line));
+    regex.push('$');
+    Some(Regex::new(&regex).map(|regex| IgnoreRule {
+        regex,
+        negate,
+        dir_only,
+    }))
+}
+
+/// 通配符转换为正则
+fn glob_to_regex(glob: &str) -> String {
+    let chars: Vec<char> = glob.chars().collect();
+    let mut regex = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                if chars.get(i + 2) == Some(&'/') {
+                    // **/ 匹配零或多级文件夹
+                    regex.push_str("(?:.*/)?");
+                    i += 3;
+                } else {
+                    regex.push_str(".*");
+                    i += 2;
+                }
+                continue;
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '[' => match chars[i..].iter().position(|x| *x == ']') {
+                Some(end) if end > 1 => {
+                    regex.push('[');
+                    let mut class: String = chars[i + 1..i + end].iter().collect();
+                    if let Some(x) = class.strip_prefix('!') {
+                        class = format!("^{}", x);
+                    }
+                    regex.push_str(&class.replace('\\', "\\\\"));
+                    regex.push(']');
+                    i += end + 1;
+                    continue;
+                }
+                _ => regex.push_str("\\["),
+            },
+            '\\' if i + 1 < chars.len() => {
+                regex.push_str(&regex::escape(&chars[i + 1].to_string()));
+                i += 2;
+                continue;
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+        i += 1;
+    }
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn includes(dirs: &[&str]) -> IgnoreRules {
+        IgnoreRules {
+            rules: Vec::new(),
+            includes: dirs.iter().filter_map(|x| normalize_include(x)).collect(),
+        }
+    }
+
+    #[test]
+    fn unanchored_pattern_matches_any_level() {
+        let rules = IgnoreRules::new(&["*.log", "# 注释", ""]);
+        assert!(rules.is_ignored("/a.log"));
+        assert!(rules.is_ignored("/x/y/a.log"));
+        assert!(!rules.is_ignored("/a.txt"));
+    }
+
+    #[test]
+    fn anchored_and_dir_only_patterns() {
+        let rules = IgnoreRules::new(&["/docs/*.md", "build/", "**/tmp/"]);
+        assert!(rules.is_ignored("/docs/a.md"));
+        assert!(!rules.is_ignored("/x/docs/a.md"));
+        assert!(!rules.is_ignored("/docs/sub/a.md"));
+        // 仅匹配文件夹 文件夹下的内容一并忽略
+        assert!(rules.is_ignored("/build/"));
+        assert!(rules.is_ignored("/a/build/out.o"));
+        assert!(!rules.is_ignored("/build"));
+        assert!(rules.is_ignored("/a/b/tmp/"));
+        assert!(rules.is_ignored("/tmp/x.txt"));
+    }
+
+    #[test]
+    fn later_rules_take_precedence() {
+        let rules = IgnoreRules::new(&["*.log", "!keep.log"]);
+        assert!(rules.is_ignored("/a.log"));
+        assert!(!rules.is_ignored("/keep.log"));
+        let rules = IgnoreRules::new(&["!keep.log", "*.log"]);
+        assert!(rules.is_ignored("/keep.log"));
+    }
+
+    #[test]
+    fn wildcards_and_classes() {
+        let rules = IgnoreRules::new(&["a?.txt", "[!b]c.txt", "\\#x"]);
+        assert!(rules.is_ignored("/a1.txt"));
+        assert!(!rules.is_ignored("/a12.txt"));
+        assert!(rules.is_ignored("/ac.txt"));
+        assert!(!rules.is_ignored("/bc.txt"));
+        assert!(rules.is_ignored("/#x"));
+    }
+
+    #[test]
+    fn normalize_includes() {
+        assert_eq!(normalize_include(" a\\b/ ").as_deref(), Some("/a/b/"));
+        assert_eq!(normalize_include("/a").as_deref(), Some("/a/"));
+        assert_eq!(normalize_include("/"), None);
+        assert_eq!(normalize_include(""), None);
+    }
+
+    #[test]
+    fn selected_sub_dirs() {
+        let rules = includes(&["a/b"]);
+        // 选择的子文件夹的上级文件夹不算在外
+        assert!(!rules.is_unselected("/a/"));
+        assert!(!rules.is_unselected("/a/b/"));
+        assert!(!rules.is_unselected("/a/b/c.txt"));
+        assert!(rules.is_unselected("/a/c.txt"));
+        assert!(rules.is_unselected("/c/"));
+        assert!(rules.is_ignored("/c/d.txt"));
+        assert!(rules.is_partial("/a/"));
+        assert!(!rules.is_partial("/a/b/"));
+        assert!(!includes(&[]).is_unselected("/c/"));
+    }
+
+    #[tokio::test]
+    async fn load_ignore_file_and_patterns() {
+        let dir = tempfile::tempdir().unwrap();
+        tokio::fs::write(dir.path().join(IGNORE_FILE), "*.log\n").await.unwrap();
+        let local_dir = dir.path().to_string_lossy().to_string();
+        let patterns = vec!["*.tmp".to_string()];
+        let includes = vec!["/a/".to_string()];
+        let rules = IgnoreRules::load(&local_dir, Some(&patterns), Some(&includes)).await;
+        assert!(rules.is_ignored("/a/x.log"));
+        assert!(rules.is_ignored("/a/x.tmp"));
+        assert!(!rules.is_ignored("/a/x.txt"));
+        assert!(rules.is_ignored("/b/x.txt"));
+    }
+}
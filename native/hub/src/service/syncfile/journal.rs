@@ -0,0 +1,307 @@
+use crate::common::global_data::GlobalData;
+use crate::messages::syncfile::RemoteVersionMsg;
+use crate::service::syncfile::crypto::remote_path;
+use crate::service::syncfile::diff::local_path;
+use crate::service::syncfile::{SyncFileService, SyncState};
+use ahash::{AHashMap, AHashSet};
+use anyhow::Result;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+const SYNC_JOURNAL_PREFIX: &str = "syncJournal";
+// 日志最短保存间隔 未保存的记录在恢复时按未完成处理
+const SAVE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// 已上传的文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct UploadedFile {
+    // 修改时间 ms
+    modified: u128,
+    // 文件内容sha256 文件夹为空
+    hash: Option<String>,
+    // 文件大小 文件夹为空
+    size: Option<u64>,
+}
+
+/// 同步日志
+/// 同步开始时记录计划，执行中记录已完成的远端修改，远端属性文件写入后删除
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SyncJournal {
+    run_id: u64,
+    // 计划删除的远端文件
+    delete_remote: Vec<String>,
+    // 已上传的文件 k: 文件路径
+    uploaded: AHashMap<String, UploadedFile>,
+    // 已删除的远端文件
+    deleted: AHashSet<String>,
+    // 已移入历史版本的记录
+    versions: Vec<RemoteVersionMsg>,
+}
+
+/// 同步日志写入
+pub(super) struct JournalWriter {
+    global_data: GlobalData,
+    key: String,
+    journal: SyncJournal,
+    // 上次保存时间
+    saved: Instant,
+}
+
+impl JournalWriter {
+    /// 开始同步 保存同步计划
+    pub(super) async fn begin(
+        global_data: &GlobalData,
+        remote_dir: &str,
+        run_id: u64,
+        delete_remote: &[String],
+    ) -> Result<Self> {
+        let mut writer = Self {
+            global_data: global_data.clone(),
+            key: journal_key(remote_dir),
+            journal: SyncJournal {
+                run_id,
+                delete_remote: delete_remote.to_vec(),
+                ..Default::default()
+            },
+            saved: Instant::now(),
+        };
+        writer.save(true).await?;
+        Ok(writer)
+    }
+
+    /// 记录已上传的文件 文件夹hash与大小为空
+    pub(super) async fn uploaded(
+        &mut self,
+        file: &str,
+        modified: u128,
+        hash: Option<String>,
+        size: Option<u64>,
+    ) -> Result<()> {
+        self.journal
+            .uploaded
+            .insert(file.to_string(), UploadedFile { modified, hash, size });
+        self.save(false).await
+    }
+
+    /// 记录已删除的远端文件
+    pub(super) async fn deleted(&mut self, file: &str) -> Result<()> {
+        self.journal.deleted.insert(file.to_string());
+        self.save(false).await
+    }
+
+    /// 记录移入历史版本的文件 随下一条记录保存
+    pub(super) fn versions(&mut self, versions: &[RemoteVersionMsg]) {
+        self.journal.versions.extend_from_slice(versions);
+    }
+
+    /// 远端属性文件已写入 删除日志
+    pub(super) async fn finish(self) -> Result<()> {
+        self.global_data.del_data(self.key).await
+    }
+
+    async fn save(&mut self, force: bool) -> Result<()> {
+        if !force && self.saved.elapsed() < SAVE_INTERVAL {
+            return Ok(());
+        }
+        self.global_data.set_data(self.key.clone(), &self.journal).await?;
+        self.saved = Instant::now();
+        Ok(())
+    }
+}
+
+impl SyncFileService {
+    /// 恢复中途退出的同步
+    /// 补全已完成的远端修改并写入远端属性文件，本地已删除的文件继续删除远端
+    /// 返回是否更新了远端属性文件
+    pub(super) async fn recover_journal(
+        global_data: &GlobalData,
+        state: &SyncState,
+        remote_dir: &str,
+    ) -> Result<bool> {
+        let key = journal_key(remote_dir);
+        let Some(journal) = global_data.get_data::<SyncJournal>(key.clone()).await else {
+            return Ok(false);
+        };
+        let local_dir = state.file_sync.files.get(remote_dir);
+        let empty = journal.uploaded.is_empty() && journal.delete_remote.is_empty();
+        let Some(local_dir) = local_dir.filter(|_| !empty) else {
+            global_data.del_data(key).await?;
+            return Ok(false);
+        };
+        warn!("文件夹{}上次同步未完成，恢复同步记录", local_dir);
+        let storage = state.storage(remote_dir)?;
+        let cipher = state.cipher(remote_dir);
+        let mut metadata = state.remote_metadata(remote_dir).await?;
+        let mut versions = journal.versions;
+        for file in &journal.delete_remote {
+            if !journal.deleted.contains(file) {
+                // 本地又出现的文件不再删除远端
+                if local_path(local_dir, file).exists() {
+                    continue;
+                }
+                // 远端文件可能已删除 失败时忽略
+                let result = match state.config.remote_versions {
+                    true => {
+                        let records = Self::version_records(&metadata, file, journal.run_id);
+                        Self::move_to_version(storage, cipher, remote_dir, file, journal.run_id)
+                            .await
+                            .map(|_| versions.extend(records))
+                    }
+                    false => storage.delete(&remote_path(cipher, remote_dir, file)).await,
+                };
+                if let Err(e) = result {
+                    warn!("删除远端文件{}失败: {}", file, e);
+                }
+            }
+            Self::remove_remote_entry(&mut metadata, file);
+        }
+        for (file, uploaded) in journal.uploaded {
+            if let Some(hash) = uploaded.hash {
+                metadata.hashes.insert(file.clone(), hash);
+            }
+            if let Some(size) = uploaded.size {
+                metadata.sizes.insert(file.clone(), size);
+            }
+            metadata.files.insert(file, uploaded.modified);
+        }
+        Self::add_remote_versions(storage, cipher, &mut metadata, remote_dir, versions).await;
+        metadata.last_time = metadata.files.values().max().copied().unwrap_or(0);
+        Self::update_remote_metadata(&metadata, remote_dir, storage, cipher).await?;
+        global_data.del_data(key).await?;
+        Ok(true)
+    }
+}
+
+fn journal_key(remote_dir: &str) -> String {
+    format!("{}-{}", SYNC_JOURNAL_PREFIX, remote_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::WEBDAV_SYNC_DIR;
+    use crate::messages::syncfile::{ConflictPolicyMsg, SyncConfigMsg};
+    use crate::service::syncfile::account::DEFAULT_ACCOUNT;
+    use crate::service::syncfile::hash::HashCache;
+    use crate::service::syncfile::limit::RateLimiter;
+    use crate::service::syncfile::metadata_cache::MetadataCache;
+    use crate::service::syncfile::trash::SyncTrash;
+    use crate::service::syncfile::watcher::LocalWatcher;
+    use crate::service::syncfile::{storage, LocalRemoteFileMappingDO, RemoteFileMedata};
+    use std::path::Path;
+    use std::sync::Arc;
+
+    const REMOTE_DIR: &str = "docs/";
+
+    /// 以临时文件夹作为本地文件夹及远端存储
+    async fn sync_state(root: &Path) -> (GlobalData, SyncState) {
+        let global_data = GlobalData::open(root.join("global.db")).await.unwrap();
+        let local_dir = root.join("local");
+        tokio::fs::create_dir_all(&local_dir).await.unwrap();
+        let storage = storage::local(&root.join("remote")).await;
+        storage.mkdir(&format!("{}{}", WEBDAV_SYNC_DIR, REMOTE_DIR)).await.unwrap();
+        let mut file_sync = LocalRemoteFileMappingDO::default();
+        file_sync
+            .files
+            .insert(REMOTE_DIR.to_string(), local_dir.to_string_lossy().to_string());
+        let state = SyncState {
+            file_sync,
+            storages: AHashMap::from_iter([(DEFAULT_ACCOUNT.to_string(), storage)]),
+            remote_accounts: AHashMap::new(),
+            remote_metadata: MetadataCache::default(),
+            config: SyncConfigMsg {
+                conflict_policy: ConflictPolicyMsg::KeepBoth,
+                trash_days: 0,
+                remote_versions: false,
+                upload_limit: 0,
+                download_limit: 0,
+                skip_metered: false,
+            },
+            upload_limit: Arc::new(RateLimiter::new(0)),
+            download_limit: Arc::new(RateLimiter::new(0)),
+            hash_cache: HashCache::default(),
+            trash: SyncTrash::default(),
+            watcher: LocalWatcher::new(),
+        };
+        (global_data, state)
+    }
+
+    #[tokio::test]
+    async fn recover_interrupted_sync() {
+        let dir = tempfile::tempdir().unwrap();
+        let (global_data, state) = sync_state(dir.path()).await;
+        let storage = state.storage(REMOTE_DIR).unwrap();
+        let mut metadata = RemoteFileMedata::default();
+        for file in ["/old.txt", "/keep.txt", "/done.txt"] {
+            metadata.files.insert(file.to_string(), 1);
+            storage
+                .write(&remote_path(None, REMOTE_DIR, file), b"remote".to_vec())
+                .await
+                .unwrap();
+        }
+        SyncFileService::update_remote_metadata(&metadata, REMOTE_DIR, storage, None)
+            .await
+            .unwrap();
+        // 中途退出时/done.txt已删除，/old.txt尚未删除，/keep.txt本地又出现
+        tokio::fs::write(local_path(&state.file_sync.files[REMOTE_DIR], "/keep.txt"), b"local")
+            .await
+            .unwrap();
+        storage.delete(&remote_path(None, REMOTE_DIR, "/done.txt")).await.unwrap();
+        let journal = SyncJournal {
+            run_id: 1,
+            delete_remote: vec!["/old.txt".to_string(), "/keep.txt".to_string(), "/done.txt".to_string()],
+            uploaded: AHashMap::from_iter([(
+                "/new.txt".to_string(),
+                UploadedFile {
+                    modified: 5,
+                    hash: Some("hash".to_string()),
+                    size: Some(3),
+                },
+            )]),
+            deleted: AHashSet::from_iter(["/done.txt".to_string()]),
+            versions: Vec::new(),
+        };
+        global_data.set_data(journal_key(REMOTE_DIR), &journal).await.unwrap();
+
+        assert!(SyncFileService::recover_journal(&global_data, &state, REMOTE_DIR).await.unwrap());
+        let metadata = state.remote_metadata(REMOTE_DIR).await.unwrap();
+        let mut files: Vec<&String> = metadata.files.keys().collect();
+        files.sort();
+        assert_eq!(files, ["/keep.txt", "/new.txt"]);
+        assert_eq!(metadata.hashes["/new.txt"], "hash");
+        assert_eq!(metadata.sizes["/new.txt"], 3);
+        assert_eq!(metadata.last_time, 5);
+        assert!(storage.read(&remote_path(None, REMOTE_DIR, "/old.txt")).await.is_err());
+        assert!(storage.read(&remote_path(None, REMOTE_DIR, "/keep.txt")).await.is_ok());
+        // 日志已删除 不重复恢复
+        assert!(global_data.get_data::<SyncJournal>(journal_key(REMOTE_DIR)).await.is_none());
+        assert!(!SyncFileService::recover_journal(&global_data, &state, REMOTE_DIR).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn empty_journal_is_discarded() {
+        let dir = tempfile::tempdir().unwrap();
+        let (global_data, state) = sync_state(dir.path()).await;
+        let writer = JournalWriter::begin(&global_data, REMOTE_DIR, 1, &[]).await.unwrap();
+        assert!(global_data.get_data::<SyncJournal>(journal_key(REMOTE_DIR)).await.is_some());
+        // 远端属性文件不存在 空日志不应读取远端
+        assert!(!SyncFileService::recover_journal(&global_data, &state, REMOTE_DIR).await.unwrap());
+        assert!(global_data.get_data::<SyncJournal>(journal_key(REMOTE_DIR)).await.is_none());
+        writer.finish().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn journal_saves_plan_immediately() {
+        let dir = tempfile::tempdir().unwrap();
+        let (global_data, _state) = sync_state(dir.path()).await;
+        let deletes = vec!["/a.txt".to_string()];
+        let mut writer = JournalWriter::begin(&global_data, REMOTE_DIR, 7, &deletes).await.unwrap();
+        writer.deleted("/a.txt").await.unwrap();
+        let journal: SyncJournal = global_data.get_data(journal_key(REMOTE_DIR)).await.unwrap();
+        assert_eq!(journal.run_id, 7);
+        assert_eq!(journal.delete_remote, deletes);
+        writer.finish().await.unwrap();
+        assert!(global_data.get_data::<SyncJournal>(journal_key(REMOTE_DIR)).await.is_none());
+    }
+}
@@ -0,0 +1,108 @@
+use crate::service::syncfile::storage::ByteStream;
+use futures::StreamExt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 传输限速 同一方向的并行传输共享
+#[derive(Debug)]
+pub(super) struct RateLimiter {
+    // 每秒字节数 为0时不限速
+    rate: AtomicU64,
+    // 已发送的数据按速率计算可继续发送的时间
+    next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    /// 以KiB/s构造 为0时不限速
+    pub(super) fn new(kib: u32) -> Self {
+        let limiter = Self {
+            rate: AtomicU64::new(0),
+            next: Mutex::new(Instant::now()),
+        };
+        limiter.set(kib);
+        limiter
+    }
+
+    /// 修改速率 KiB/s
+    pub(super) fn set(&self, kib: u32) {
+        self.rate.store(kib as u64 * 1024, Ordering::Relaxed);
+    }
+
+    /// 记录传输的字节数 超出速率时等待
+    pub(super) async fn consume(&self, bytes: usize) {
+        let rate = self.rate.load(Ordering::Relaxed);
+        if rate == 0 {
+            return;
+        }
+        let wait = {
+            let mut next = self.next.lock().unwrap();
+            let now = Instant::now();
+            let start = (*next).max(now);
+            *next = start + Duration::from_secs_f64(bytes as f64 / rate as f64);
+            start - now
+        };
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// 限速读取流
+    pub(super) fn limit(self: &Arc<Self>, stream: ByteStream) -> ByteStream {
+        let limiter = self.clone();
+        Box::pin(stream.then(move |chunk| {
+            let limiter = limiter.clone();
+            async move {
+                if let Ok(chunk) = &chunk {
+                    limiter.consume(chunk.len()).await;
+                }
+                chunk
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use futures::TryStreamExt;
+
+    #[tokio::test]
+    async fn unlimited_does_not_wait() {
+        let limiter = RateLimiter::new(0);
+        let start = Instant::now();
+        limiter.consume(100 * 1024 * 1024).await;
+        limiter.consume(100 * 1024 * 1024).await;
+        assert!(start.elapsed() < Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn waits_once_rate_is_exceeded() {
+        // 100KiB/s 首次传输不等待，之后按已传输的数据等待
+        let limiter = RateLimiter::new(100);
+        let start = Instant::now();
+        limiter.consume(10 * 1024).await;
+        assert!(start.elapsed() < Duration::from_millis(50));
+        limiter.consume(10 * 1024).await;
+        limiter.consume(10 * 1024).await;
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(190), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(1000), "{:?}", elapsed);
+
+        // 修改为不限速后立即生效
+        limiter.set(0);
+        let start = Instant::now();
+        limiter.consume(10 * 1024 * 1024).await;
+        assert!(start.elapsed() < Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn limited_stream_keeps_data() {
+        let limiter = Arc::new(RateLimiter::new(1024));
+        let chunks = vec![Ok(Bytes::from_static(b"ab")), Ok(Bytes::from_static(b"cd"))];
+        let stream: ByteStream = Box::pin(futures::stream::iter(chunks));
+        let result: Vec<Bytes> = limiter.limit(stream).try_collect().await.unwrap();
+        assert_eq!(result.concat(), b"abcd");
+    }
+}
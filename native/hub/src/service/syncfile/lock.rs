@@ -0,0 +1,217 @@
+use crate::common::error::ServiceError;
+use crate::common::utils::{get_machine_id, get_user_name};
+use crate::common::WEBDAV_SYNC_DIR;
+use crate::service::syncfile::now_millis;
+use crate::service::syncfile::storage::SyncStorage;
+use anyhow::Result;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::time::Duration;
+
+const LOCK_FILE: &str = ".sync_lock";
+// 锁有效期 同步期间定期续期，进程退出后过期即可被其它设备获取
+const LOCK_LEASE: Duration = Duration::from_secs(5 * 60);
+// 续期间隔
+const RENEW_INTERVAL: Duration = Duration::from_secs(60);
+// 写入锁后等待其它设备的写入完成再确认
+const CONFIRM_DELAY: Duration = Duration::from_millis(500);
+
+/// 远端锁文件内容
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+struct LockInfo {
+    // 持有锁的设备
+    machine: String,
+    // 持有锁的用户 仅用于提示
+    user: String,
+    // 本次加锁标识 区分同一设备的多次加锁
+    token: u64,
+    // 过期时间 ms
+    expire: u64,
+}
+
+/// 远端文件夹同步锁
+/// 以锁文件记录持有的设备及过期时间，同一时间只允许一台设备修改远端文件夹
+pub(super) struct RemoteLock {
+    path: String,
+    info: LockInfo,
+}
+
+impl RemoteLock {
+    /// 获取锁 其它设备持有未过期的锁时返回错误
+    pub(super) async fn acquire(storage: &dyn SyncStorage, remote_dir: &str) -> Result<Self> {
+        let path = format!("{}{}{}", WEBDAV_SYNC_DIR, remote_dir, LOCK_FILE);
+        let machine = get_machine_id()?;
+        if let Some(current) = read_lock(storage, &path).await {
+            if current.machine != machine && current.expire > now_millis() {
+                return Err(locked_error(&current).into());
+            }
+            if current.expire <= now_millis() {
+                warn!("远端文件夹{}的锁已过期，重新获取", remote_dir);
+            }
+        }
+        let token = now_millis();
+        let mut lock = Self {
+            path,
+            info: LockInfo {
+                machine,
+                user: get_user_name(),
+                token,
+                expire: token,
+            },
+        };
+        lock.renew(storage).await?;
+        // 两台设备同时写入时以最后写入的为准
+        tokio::time::sleep(CONFIRM_DELAY).await;
+        match read_lock(storage, &lock.path).await {
+            Some(current) if current == lock.info => Ok(lock),
+            Some(current) => Err(locked_error(&current).into()),
+            None => Err(ServiceError::Remote("无法写入远端同步锁".to_string()).into()),
+        }
+    }
+
+    /// 持有锁执行操作 期间定期续期，锁被其它设备获取时中止
+    pub(super) async fn hold<T>(
+        &self,
+        storage: &dyn SyncStorage,
+        f: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        let path = self.path.clone();
+        let mut info = self.info.clone();
+        let renew = async {
+            loop {
+                tokio::time::sleep(RENEW_INTERVAL).await;
+                match read_lock(storage, &path).await {
+                    Some(current) if current.token != info.token || current.machine != info.machine => {
+                        return Err::<(), _>(locked_error(&current));
+                    }
+                    _ => {}
+                }
+                info.expire = now_millis() + LOCK_LEASE.as_millis() as u64;
+                if let Err(e) = write_lock(storage, &path, &info).await {
+                    warn!("远端同步锁续期失败: {}", e);
+                }
+            }
+        };
+        tokio::select! {
+            result = f => result,
+            Err(e) = renew => Err(e.into()),
+        }
+    }
+
+    /// 释放锁
+    pub(super) async fn release(self, storage: &dyn SyncStorage) {
+        // 已被其它设备获取时不删除
+        match read_lock(storage, &self.path).await {
+            Some(current) if current.token == self.info.token && current.machine == self.info.machine => {}
+            _ => return,
+        }
+        if let Err(e) = storage.delete(&self.path).await {
+            warn!("释放远端同步锁失败: {}", e);
+        }
+    }
+
+    /// 延长有效期
+    async fn renew(&mut self, storage: &dyn SyncStorage) -> Result<()> {
+        self.info.expire = now_millis() + LOCK_LEASE.as_millis() as u64;
+        write_lock(storage, &self.path, &self.info).await
+    }
+}
+
+/// 读取锁文件 不存在或无法解析时为空
+async fn read_lock(storage: &dyn SyncStorage, path: &str) -> Option<LockInfo> {
+    let data = storage.read(path).await.ok()?;
+    serde_json::from_slice(&data).ok()
+}
+
+async fn write_lock(storage: &dyn SyncStorage, path: &str, info: &LockInfo) -> Result<()> {
+    storage.write(path, serde_json::to_vec(info)?).await
+}
+
+/// 被其它设备锁定的错误
+fn locked_error(info: &LockInfo) -> ServiceError {
+    let minutes = info.expire.saturating_sub(now_millis()).div_ceil(60 * 1000);
+    let machine = info.machine.get(..8).unwrap_or(&info.machine);
+    ServiceError::Locked(format!(
+        "文件夹正在由其它设备同步(用户{}，设备{})，锁将在{}分钟内过期，请稍后重试",
+        info.user, machine, minutes
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::syncfile::storage;
+
+    const REMOTE_DIR: &str = "docs/";
+
+    async fn remote(root: &std::path::Path) -> Box<dyn SyncStorage> {
+        let storage = storage::local(root).await;
+        storage.mkdir(&format!("{}{}", WEBDAV_SYNC_DIR, REMOTE_DIR)).await.unwrap();
+        storage
+    }
+
+    fn lock_path() -> String {
+        format!("{}{}{}", WEBDAV_SYNC_DIR, REMOTE_DIR, LOCK_FILE)
+    }
+
+    /// 其它设备持有的锁
+    async fn lock_by_other(storage: &dyn SyncStorage, expire: u64) -> LockInfo {
+        let info = LockInfo {
+            machine: "other-machine".to_string(),
+            user: "other".to_string(),
+            token: 1,
+            expire,
+        };
+        write_lock(storage, &lock_path(), &info).await.unwrap();
+        info
+    }
+
+    #[tokio::test]
+    async fn acquire_and_release() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = remote(dir.path()).await;
+        let lock = RemoteLock::acquire(storage.as_ref(), REMOTE_DIR).await.unwrap();
+        let current = read_lock(storage.as_ref(), &lock_path()).await.unwrap();
+        assert_eq!(current, lock.info);
+        assert_eq!(current.machine, get_machine_id().unwrap());
+        assert!(current.expire > now_millis());
+
+        let result = lock.hold(storage.as_ref(), async { Ok(1) }).await.unwrap();
+        assert_eq!(result, 1);
+        // 同一设备可重新获取
+        let again = RemoteLock::acquire(storage.as_ref(), REMOTE_DIR).await.unwrap();
+        lock.release(storage.as_ref()).await;
+        assert!(read_lock(storage.as_ref(), &lock_path()).await.is_some());
+        again.release(storage.as_ref()).await;
+        assert!(read_lock(storage.as_ref(), &lock_path()).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn locked_by_other_machine() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = remote(dir.path()).await;
+        lock_by_other(storage.as_ref(), now_millis() + 60 * 1000).await;
+        let err = RemoteLock::acquire(storage.as_ref(), REMOTE_DIR).await.err().unwrap();
+        assert!(matches!(err.downcast_ref::<ServiceError>(), Some(ServiceError::Locked(_))));
+    }
+
+    #[tokio::test]
+    async fn expired_lock_is_taken_over() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = remote(dir.path()).await;
+        lock_by_other(storage.as_ref(), now_millis() - 1).await;
+        let lock = RemoteLock::acquire(storage.as_ref(), REMOTE_DIR).await.unwrap();
+        assert_eq!(read_lock(storage.as_ref(), &lock_path()).await.unwrap(), lock.info);
+    }
+
+    #[tokio::test]
+    async fn release_keeps_lock_taken_by_other() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = remote(dir.path()).await;
+        let lock = RemoteLock::acquire(storage.as_ref(), REMOTE_DIR).await.unwrap();
+        let other = lock_by_other(storage.as_ref(), now_millis() + 60 * 1000).await;
+        lock.release(storage.as_ref()).await;
+        assert_eq!(read_lock(storage.as_ref(), &lock_path()).await.unwrap(), other);
+    }
+}
@@ -0,0 +1,40 @@
+use crate::service::syncfile::RemoteFileMedata;
+use ahash::AHashMap;
+
+/// 缓存的远端文件夹属性
+#[derive(Debug, Clone)]
+struct CachedMetadata {
+    // 属性文件版本标识
+    version: String,
+    metadata: RemoteFileMedata,
+}
+
+/// 远端文件夹属性缓存
+/// 以属性文件的版本标识(etag或修改时间)判断是否变化，未变化时不重新下载
+#[derive(Debug, Default)]
+pub(super) struct MetadataCache {
+    // k: (账户名称, 远端路径)
+    entries: AHashMap<(String, String), CachedMetadata>,
+}
+
+impl MetadataCache {
+    /// 获取版本一致的缓存
+    pub(super) fn get(&self, account: &str, dir: &str, version: &str) -> Option<&RemoteFileMedata> {
+        self.entries
+            .get(&(account.to_string(), dir.to_string()))
+            .filter(|x| x.version == version)
+            .map(|x| &x.metadata)
+    }
+
+    /// 记录远端文件夹属性
+    pub(super) fn insert(&mut self, account: &str, dir: &str, version: String, metadata: RemoteFileMedata) {
+        self.entries
+            .insert((account.to_string(), dir.to_string()), CachedMetadata { version, metadata });
+    }
+
+    /// 移除账户下已不存在的远端文件夹
+    pub(super) fn retain(&mut self, account: &str, dirs: &AHashMap<String, RemoteFileMedata>) {
+        self.entries
+            .retain(|(a, dir), _| a != account || dirs.contains_key(dir));
+    }
+}
@@ -0,0 +1,1594 @@
+mod account;
+mod crypto;
+mod diff;
+mod hash;
+mod ignore;
+mod journal;
+mod limit;
+mod lock;
+mod metadata_cache;
+mod network;
+mod scheduler;
+mod secrets;
+mod storage;
+mod trash;
+mod version;
+mod watcher;
+
+use crate::common::error::ServiceError;
+use crate::common::event_bus::topic;
+use crate::common::global_data::GlobalData;
+use crate::common::utils::{get_machine_id, sha256};
+use crate::common::WEBDAV_SYNC_DIR;
+use crate::messages::common::{BoolMsg, StringMsg, UintFiveMsg};
+use crate::messages::event::SyncFinishedMsg;
+use crate::messages::syncfile::{AddLocalForRemoteMsg, AddSyncDirMsg, ApplySyncDirMsg, ConflictPolicyMsg, FileMsg, FileStatusEnumMsg, ListFileMsg, SyncAccountMsg, SyncAccountsMsg, SyncConfigMsg, RemoteVersionMsg, RemoteVersionReqMsg, RemoteVersionsMsg, RestoreRemoteVersionMsg, SyncFileDetailMsg, SyncIncludesMsg, SyncPreviewMsg, SyncRunMsg, SyncRunsMsg, SyncTrashMsg, TrashReasonEnumMsg, WebDavConfigMsg};
+use crate::service::syncfile::account::DEFAULT_ACCOUNT;
+use crate::service::syncfile::crypto::{cipher_secret, remote_path, CryptoInfo, SyncCipher};
+use crate::service::syncfile::diff::{local_path, FolderDiff};
+use crate::service::syncfile::hash::HashCache;
+use crate::service::syncfile::ignore::{normalize_include, IgnoreRules};
+use crate::service::syncfile::journal::JournalWriter;
+use crate::service::syncfile::limit::RateLimiter;
+use crate::service::syncfile::lock::RemoteLock;
+use crate::service::syncfile::metadata_cache::MetadataCache;
+use crate::service::syncfile::scheduler::SyncScheduler;
+use crate::service::syncfile::secrets::SyncSecrets;
+use crate::service::syncfile::storage::{ByteStream, SyncStorage};
+use crate::service::syncfile::trash::SyncTrash;
+use crate::service::syncfile::version::VERSIONS_DIR;
+use crate::service::syncfile::watcher::LocalWatcher;
+use crate::service::service::{FuncDescriptor, FuncTimeout, ImmService, Progress, Service, ServiceDescriptor, EMPTY_MSG};
+use crate::{
+    async_func_notype, async_func_typeno, async_func_typetype, async_progress_func_typetype, func_end,
+    func_notype,
+};
+use ahash::{AHashMap, AHashSet};
+use anyhow::Result;
+use async_trait::async_trait;
+use bytes::Bytes;
+use filetime::FileTime;
+use log::warn;
+use futures::{FutureExt, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::ops::Add;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::fs::{create_dir_all, metadata, File};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::Mutex;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct AccountInfo {
+    user: String,
+    // 密码 保存在系统密钥服务中，首次使用时读取
+    #[serde(skip)]
+    passwd: String,
+    // 旧版本明文保存的密码 迁移到系统密钥服务后清空
+    #[serde(rename = "passwd", default, skip_serializing_if = "Option::is_none")]
+    legacy_passwd: Option<String>,
+    url: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LocalRemoteFileMappingDO {
+    // 存储文件路径
+    // k: 远端路径 v: 本地路径
+    files: AHashMap<String, String>,
+    // 设定的忽略规则 k: 远端路径
+    #[serde(default)]
+    ignores: AHashMap<String, Vec<String>>,
+    // 本机选择同步的子文件夹 k: 远端路径 未设定时同步全部
+    #[serde(default)]
+    includes: AHashMap<String, Vec<String>>,
+    // 加密文件夹的密钥 k: 远端路径
+    #[serde(default)]
+    keys: AHashMap<String, SyncCipher>,
+    // 文件夹所属账户 k: 远端路径 v: 账户名称 缺省为默认账户
+    #[serde(default)]
+    accounts: AHashMap<String, String>,
+}
+
+/// 远端文件属性
+/// 加密文件夹仅tag、last_time及加密信息为明文，其余内容加密后存于sealed
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct RemoteFileMedata {
+    // 最初文件地址(方便使用)
+    tag: String,
+    // 远端最新一次修改时间 ms
+    last_time: u128,
+    // 远端所有文件路径+最新修改时间
+    #[serde(default)]
+    files: AHashMap<String, u128>,
+    // 文件内容sha256 k: 文件路径 不含文件夹
+    #[serde(default)]
+    hashes: AHashMap<String, String>,
+    // 文件大小 k: 文件路径 不含文件夹
+    #[serde(default)]
+    sizes: AHashMap<String, u64>,
+    // 历史版本 最新的在前
+    #[serde(default)]
+    versions: Vec<RemoteVersionMsg>,
+    // 加密信息 未加密时为空
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    crypto: Option<CryptoInfo>,
+    // 加密后的文件属性
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sealed: Option<String>,
+    // 本地扫描时被忽略的路径 不写入远端属性文件
+    #[serde(skip)]
+    ignored: Vec<String>,
+}
+
+/// 本地文件属性
+type LocalFileMetadata = RemoteFileMedata;
+
+/// 文件同步服务
+pub struct SyncFileService {
+    // 全局数据存储
+    global_data: GlobalData,
+    // 账号信息 k: 账户名称 与只读处理器、定时同步共享
+    accounts: Arc<RwLock<AHashMap<String, AccountInfo>>>,
+    // 同步状态 与定时同步共享
+    state: Arc<Mutex<SyncState>>,
+    // timer 与只读处理器共享
+    timer: Arc<RwLock<Option<u32>>>,
+    // 定时同步
+    scheduler: SyncScheduler,
+    // 账户密码及文件夹密钥 与只读处理器、定时同步共享
+    secrets: SyncSecrets,
+}
+
+/// 同步状态
+/// 手动操作与定时同步共享，定时同步时若已被占用则跳过
+struct SyncState {
+    // 本地文件与远端文件地址映射关系
+    file_sync: LocalRemoteFileMappingDO,
+    // 各账户的远端存储 k: 账户名称
+    storages: AHashMap<String, Box<dyn SyncStorage>>,
+    // 最近一次列出的远端文件夹所属账户 k: 远端路径
+    remote_accounts: AHashMap<String, String>,
+    // 远端文件夹属性缓存
+    remote_metadata: MetadataCache,
+    // 同步设置
+    config: SyncConfigMsg,
+    // 上传限速
+    upload_limit: Arc<RateLimiter>,
+    // 下载限速
+    download_limit: Arc<RateLimiter>,
+    // 本地文件hash缓存
+    hash_cache: HashCache,
+    // 本地回收站
+    trash: SyncTrash,
+    // 本地文件夹监听
+    watcher: LocalWatcher,
+}
+
+impl SyncState {
+    /// 获取文件夹的忽略规则
+    async fn ignore_rules(&self, remote_dir: &str, local_dir: &str) -> IgnoreRules {
+        let includes = self.file_sync.includes.get(remote_dir);
+        IgnoreRules::load(local_dir, self.file_sync.ignores.get(remote_dir), includes).await
+    }
+
+    /// 获取文件夹的加密密钥 未加密时为空
+    fn cipher(&self, remote_dir: &str) -> Option<&SyncCipher> {
+        self.file_sync.keys.get(remote_dir)
+    }
+
+    /// 获取远端目录文件属性 加密时解密
+    async fn remote_metadata(&self, remote_dir: &str) -> Result<RemoteFileMedata> {
+        let metadata = SyncFileService::get_remote_dir_metadatas(self.storage(remote_dir)?, remote_dir).await?;
+        SyncFileService::open_metadata(metadata, self.cipher(remote_dir))
+    }
+}
+
+/// 同步进度
+struct SyncProgress {
+    progress: Progress,
+    current: u32,
+    total: u32,
+    // 同步日志 记录已完成的远端修改
+    journal: JournalWriter,
+}
+
+impl SyncProgress {
+    /// 完成一个文件
+    fn step(&mut self, file: &str) {
+        self.current += 1;
+        self.progress.report(self.current, self.total, file);
+    }
+}
+
+/// 只读请求处理 不需要等待同步等耗时操作
+struct SyncFileReader {
+    accounts: Arc<RwLock<AHashMap<String, AccountInfo>>>,
+    secrets: SyncSecrets,
+    timer: Arc<RwLock<Option<u32>>>,
+    runs: Arc<RwLock<VecDeque<SyncRunMsg>>>,
+}
+
+const NAME: &str = "SyncFileService";
+const TIMER_CACHE: &str = "timerCache";
+const ACCOUNT_CACHE: &str = "accountCache";
+const ACCOUNTS_CACHE: &str = "syncAccountsCache";
+const SYNC_FILE_PREFIX: &str = "syncFilePrefix";
+const SYNC_CONFIG_CACHE: &str = "syncConfigCache";
+// 回收站默认保留天数
+const DEFAULT_TRASH_DAYS: u32 = 30;
+const METADATA_FILE: &str = ".sync_file.db";
+// 传输中临时文件后缀
+const TEMP_SUFFIX: &str = ".nfsync-part";
+// 同时传输的文件数量
+const MAX_TRANSFERS: usize = 4;
+// 上传时每次读取的大小
+const CHUNK_SIZE: usize = 256 * 1024;
+
+/// 服务描述
+pub const DESCRIPTOR: ServiceDescriptor = ServiceDescriptor {
+    name: NAME,
+    funcs: &[
+        FuncDescriptor::new("has_account", EMPTY_MSG, "BoolMsg"),
+        FuncDescriptor::new("list_dirs", EMPTY_MSG, "ListFileMsg"),
+        FuncDescriptor::read("get_timer", EMPTY_MSG, "UintFiveMsg"),
+        FuncDescriptor::new("sync_dir", "StringMsg", "SyncFileDetailMsg").timeout(FuncTimeout::Unlimited),
+        FuncDescriptor::new("preview_sync_dir", "StringMsg", "SyncPreviewMsg"),
+        FuncDescriptor::new("apply_sync_dir", "ApplySyncDirMsg", "SyncFileDetailMsg")
+            .timeout(FuncTimeout::Unlimited),
+        FuncDescriptor::new("set_account", "WebDavConfigMsg", "BoolMsg"),
+        FuncDescriptor::new("add_sync_dir", "AddSyncDirMsg", "FileMsg"),
+        FuncDescriptor::new("add_local_file", "AddLocalForRemoteMsg", "FileMsg"),
+        FuncDescriptor::new("del_remote_dir", "StringMsg", EMPTY_MSG),
+        FuncDescriptor::new("set_timer", "UintFiveMsg", EMPTY_MSG),
+        FuncDescriptor::new("del_local_dir", "StringMsg", EMPTY_MSG),
+        FuncDescriptor::read("get_account", EMPTY_MSG, "WebDavConfigMsg"),
+        FuncDescriptor::read("list_accounts", EMPTY_MSG, "SyncAccountsMsg"),
+        FuncDescriptor::new("add_account", "SyncAccountMsg", EMPTY_MSG),
+        FuncDescriptor::new("update_account", "SyncAccountMsg", EMPTY_MSG),
+        FuncDescriptor::new("remove_account", "StringMsg", EMPTY_MSG),
+        FuncDescriptor::new("test_account", "SyncAccountMsg", "BoolMsg"),
+        FuncDescriptor::read("get_sync_runs", EMPTY_MSG, "SyncRunsMsg"),
+        FuncDescriptor::new("set_sync_config", "SyncConfigMsg", EMPTY_MSG),
+        FuncDescriptor::new("get_sync_config", EMPTY_MSG, "SyncConfigMsg"),
+        FuncDescriptor::new("list_trash", "StringMsg", "SyncTrashMsg"),
+        FuncDescriptor::new("restore_trash", "StringMsg", EMPTY_MSG),
+        FuncDescriptor::new("purge_trash", "StringMsg", EMPTY_MSG),
+        FuncDescriptor::new("list_remote_versions", "RemoteVersionReqMsg", "RemoteVersionsMsg"),
+        FuncDescriptor::new("restore_remote_version", "RestoreRemoteVersionMsg", EMPTY_MSG),
+        FuncDescriptor::new("set_includes", "SyncIncludesMsg", EMPTY_MSG),
+        FuncDescriptor::new("get_includes", "StringMsg", "SyncIncludesMsg"),
+    ],
+    platforms: &[],
+    timeout: Some(Duration::from_secs(600)),
+};
+
+#[async_trait]
+impl Service for SyncFileService {
+    async fn handle(&mut self, func: &str, req_data: Vec<u8>) -> Result<Option<Vec<u8>>> {
+        self.secrets.load().await;
+        async_func_notype!(self, func, has_account, list_dirs, get_sync_config);
+        async_func_typetype!(
+            self,
+            func,
+            req_data,
+            set_account,
+            WebDavConfigMsg,
+            add_sync_dir,
+            AddSyncDirMsg,
+            add_local_file,
+            AddLocal4RemoteMsg,
+            preview_sync_dir,
+            StringMsg,
+            list_trash,
+            StringMsg,
+            list_remote_versions,
+            RemoteVersionReqMsg,
+            test_account,
+            SyncAccountMsg,
+            get_includes,
+            StringMsg
+        );
+
+        async_func_typeno!(self, func, req_data, del_remote_dir, StringMsg, set_timer, Uint32Msg,
+            del_local_dir, StringMsg, set_sync_config, SyncConfigMsg, restore_trash, StringMsg,
+            purge_trash, StringMsg, restore_remote_version, RestoreRemoteVersionMsg,
+            add_account, SyncAccountMsg, update_account, SyncAccountMsg, remove_account, StringMsg,
+            set_includes, SyncIncludesMsg);
+
+        func_end!(func)
+    }
+
+    async fn handle_progress(
+        &mut self,
+        func: &str,
+        req_data: Vec<u8>,
+        progress: Progress,
+    ) -> Result<Option<Vec<u8>>> {
+        self.secrets.load().await;
+        async_progress_func_typetype!(self, func, req_data, sync_dir, StringMsg, progress,
+            apply_sync_dir, ApplySyncDirMsg, progress);
+        self.handle(func, req_data).await
+    }
+
+    /// 关闭服务时保存数据
+    async fn close(&mut self) -> Result<()> {
+        self.scheduler.stop();
+        let accounts = self.accounts.read().unwrap().clone();
+        let timer = *self.timer.read().unwrap();
+        let state = self.state.lock().await;
+        self.global_data.set_data(ACCOUNTS_CACHE.to_string(), &accounts).await?;
+        self.global_data.set_data(format!("{}-{}", SYNC_FILE_PREFIX, get_machine_id()?), &state.file_sync).await?;
+        self.global_data.set_data(TIMER_CACHE.to_string(), &timer).await?;
+        state.hash_cache.save(&self.global_data).await?;
+        state.trash.save(&self.global_data).await?;
+        Ok(())
+    }
+
+    fn read_handler(&self) -> Option<Arc<Box<dyn ImmService>>> {
+        Some(Arc::new(Box::new(SyncFileReader {
+            accounts: self.accounts.clone(),
+            secrets: self.secrets.clone(),
+            timer: self.timer.clone(),
+            runs: self.scheduler.runs(),
+        })))
+    }
+}
+
+#[async_trait]
+impl ImmService for SyncFileReader {
+    async fn handle(&self, func: &str, _req_data: Vec<u8>) -> Result<Option<Vec<u8>>> {
+        self.secrets.load().await;
+        func_notype!(self, func, get_account, get_timer, get_sync_runs, list_accounts);
+        func_end!(func)
+    }
+}
+
+impl SyncFileReader {
+    /// 获取定时器时间
+    fn get_timer(&self) -> Result<UintFiveMsg> {
+        Ok(UintFiveMsg {
+            value: self.timer.read().unwrap().unwrap_or(0),
+        })
+    }
+
+    /// 获取默认账户信息
+    fn get_account(&self) -> Result<WebDavConfigMsg> {
+        let accounts = self.accounts.read().unwrap();
+        let account = accounts
+            .get(DEFAULT_ACCOUNT)
+            .ok_or_else(|| ServiceError::NotLoggedIn("无账户信息".to_string()))?;
+        Ok(WebDavConfigMsg {
+            url: account.url.clone(),
+            account: account.user.clone(),
+            passwd: account.passwd.clone(),
+        })
+    }
+
+    /// 获取所有账户
+    fn list_accounts(&self) -> Result<SyncAccountsMsg> {
+        let mut accounts: Vec<SyncAccountMsg> = self
+            .accounts
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(name, account)| SyncAccountMsg {
+                name: name.clone(),
+                url: account.url.clone(),
+                account: account.user.clone(),
+                passwd: account.passwd.clone(),
+            })
+            .collect();
+        accounts.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(SyncAccountsMsg { accounts })
+    }
+
+    /// 获取定时同步记录
+    fn get_sync_runs(&self) -> Result<SyncRunsMsg> {
+        Ok(SyncRunsMsg {
+            runs: self.runs.read().unwrap().iter().cloned().collect(),
+        })
+    }
+}
+
+impl SyncFileService {
+    pub async fn new(global_data: GlobalData) -> Result<Self> {
+        // 兼容单账户版本 原账户作为默认账户
+        let (accounts, migrated) = match global_data.get_data(ACCOUNTS_CACHE.to_string()).await {
+            Some(accounts) => (accounts, false),
+            None => (
+                global_data
+                    .get_data::<AccountInfo>(ACCOUNT_CACHE.to_string())
+                    .await
+                    .map(|x| AHashMap::from_iter([(DEFAULT_ACCOUNT.to_string(), x)]))
+                    .unwrap_or_default(),
+                true,
+            ),
+        };
+        if migrated {
+            global_data.set_data(ACCOUNTS_CACHE.to_string(), &accounts).await?;
+            global_data.del_data(ACCOUNT_CACHE.to_string()).await?;
+        }
+        let file_sync: LocalRemoteFileMappingDO = global_data
+            .get_data(format!("{}-{}", SYNC_FILE_PREFIX, get_machine_id()?))
+            .await
+            .unwrap_or_default();
+        let timer: Option<u32> = global_data.get_data(TIMER_CACHE.to_string()).await;
+        let accounts = Arc::new(RwLock::new(accounts));
+        let config = global_data
+            .get_data(SYNC_CONFIG_CACHE.to_string())
+            .await
+            .unwrap_or(SyncConfigMsg {
+                conflict_policy: ConflictPolicyMsg::KeepBoth,
+                trash_days: DEFAULT_TRASH_DAYS,
+                remote_versions: false,
+                upload_limit: 0,
+                download_limit: 0,
+                skip_metered: false,
+            });
+        let state = Arc::new(Mutex::new(SyncState {
+            file_sync,
+            storages: AHashMap::new(),
+            remote_accounts: AHashMap::new(),
+            remote_metadata: MetadataCache::default(),
+            upload_limit: Arc::new(RateLimiter::new(config.upload_limit)),
+            download_limit: Arc::new(RateLimiter::new(config.download_limit)),
+            config,
+            hash_cache: HashCache::load(&global_data).await,
+            trash: SyncTrash::load(&global_data).await,
+            watcher: LocalWatcher::new(),
+        }));
+        // 密码及密钥首次使用时读取
+        let secrets = SyncSecrets::new(global_data.clone(), accounts.clone(), state.clone());
+        let mut scheduler =
+            SyncScheduler::new(global_data.clone(), state.clone(), accounts.clone(), secrets.clone());
+        scheduler.start(timer.unwrap_or(0));
+        let r = Self {
+            accounts,
+            state,
+            secrets,
+            timer: Arc::new(RwLock::new(timer)),
+            scheduler,
+            global_data,
+        };
+
+        Ok(r)
+    }
+}
+
+impl SyncFileService {
+    /// 保存定时器时间
+    async fn set_timer(&mut self, timer: UintFiveMsg) -> Result<()> {
+        *self.timer.write().unwrap() = Some(timer.value);
+        self.scheduler.start(timer.value);
+        Ok(())
+    }
+
+    /// 保存同步设置
+    async fn set_sync_config(&mut self, config: SyncConfigMsg) -> Result<()> {
+        self.global_data.set_data(SYNC_CONFIG_CACHE.to_string(), &config).await?;
+        let mut state = self.state.lock().await;
+        state.upload_limit.set(config.upload_limit);
+        state.download_limit.set(config.download_limit);
+        state.config = config;
+        Ok(())
+    }
+
+    /// 获取同步设置
+    async fn get_sync_config(&self) -> Result<SyncConfigMsg> {
+        Ok(self.state.lock().await.config.clone())
+    }
+
+    /// 测试帐号是否可用 连接所有账户
+    async fn has_account(&mut self) -> Result<BoolMsg> {
+        let accounts = self.accounts.read().unwrap().clone();
+        if accounts.is_empty() {
+            return Ok(BoolMsg { value: false });
+        }
+        // 各账户独立连接 部分账户无法连接时不影响其它账户
+        let mut state = self.state.lock().await;
+        for (name, account) in accounts {
+            match storage::connect(&account).await {
+                Ok(storage) => {
+                    state.storages.insert(name, storage);
+                }
+                Err(e) => {
+                    warn!("账户{}连接失败: {}", name, e);
+                    state.storages.remove(&name);
+                }
+            }
+        }
+        Ok(BoolMsg { value: !state.storages.is_empty() })
+    }
+
+    /// 设置默认账户信息
+    async fn set_account(&mut self, account: WebDavConfigMsg) -> Result<BoolMsg> {
+        let connected = self
+            .save_account(SyncAccountMsg {
+                name: DEFAULT_ACCOUNT.to_string(),
+                url: account.url,
+                account: account.account,
+                passwd: account.passwd,
+            })
+            .await?;
+        Ok(BoolMsg { value: connected })
+    }
+
+    /// 同步文件列表信息
+    async fn list_dirs(&mut self) -> Result<ListFileMsg> {
+        let mut state = self.state.lock().await;
+        let state = &mut *state;
+        if state.storages.is_empty() {
+            return Err(ServiceError::NotLoggedIn("无账户信息，请登录".to_string()).into());
+        }
+        // 汇总各账户的远端文件夹 属性文件未变化时使用缓存
+        // 部分账户无法列出时跳过该账户，全部失败时返回错误
+        let mut remote_files = AHashMap::new();
+        let mut remote_accounts = AHashMap::new();
+        let mut failed = AHashSet::new();
+        let mut error = None;
+        for (account, storage) in &state.storages {
+            match Self::get_remote_dirs(storage.as_ref(), account, &mut state.remote_metadata).await {
+                Ok(dirs) => {
+                    for (dir, metadata) in dirs {
+                        remote_accounts.insert(dir.clone(), account.clone());
+                        remote_files.insert(dir, metadata);
+                    }
+                }
+                Err(e) => {
+                    warn!("无法列出账户{}的远端文件夹: {}", account, e);
+                    failed.insert(account.clone());
+                    error = Some(e);
+                }
+            }
+        }
+        if let Some(e) = error
+            && failed.len() == state.storages.len()
+        {
+            return Err(e);
+        }
+        // 无法列出的账户保留上次的结果
+        for (dir, account) in &state.remote_accounts {
+            if failed.contains(account) {
+                remote_accounts.entry(dir.clone()).or_insert_with(|| account.clone());
+            }
+        }
+        state.remote_accounts = remote_accounts;
+        let state = &*state;
+        let real_remote: AHashSet<&String> = remote_files.keys().collect();
+        let local_remote: AHashSet<&String> = state.file_sync.files.keys().collect();
+        let mut result: Vec<FileMsg> = Vec::new();
+
+        // 本地有，远端没有，表示 远端数据已删除 需要提示删除本地
+        // 所属账户未连接或无法列出时无法判断，跳过
+        for file in local_remote.difference(&real_remote).filter(|x| {
+            let account = state.account(x);
+            state.storages.contains_key(account) && !failed.contains(account)
+        }) {
+            result.push(FileMsg {
+                local_dir: state.file_sync.files.get(*file).unwrap().clone(),
+                remote_dir: "".to_string(),
+                status: FileStatusEnumMsg::UPLOAD,
+                add: 0,
+                del: 0,
+                modify: 0,
+                conflict: 0,
+                encrypted: false,
+                account: state.account(file).to_string(),
+                tag: "".to_string(),
+            });
+        }
+
+        // 远端有，本地没有，表示其它设备新上传的文件夹 需要提示映射本地文件夹
+        for file in real_remote.difference(&local_remote) {
+            result.push(FileMsg {
+                local_dir: "".to_string(),
+                remote_dir: file.to_string(),
+                status: FileStatusEnumMsg::UPLOAD,
+                add: 0,
+                del: 0,
+                modify: 0,
+                conflict: 0,
+                encrypted: remote_files.get(*file).unwrap().crypto.is_some(),
+                account: state.account(file).to_string(),
+                tag: remote_files.get(*file).unwrap().tag.clone(),
+            });
+        }
+
+        // 双方都有，则需要同步操作
+        for file in local_remote.intersection(&real_remote) {
+            let local_path = state.file_sync.files.get(*file).unwrap();
+            let ignore = state.ignore_rules(file, local_path).await;
+            let l_metadata = state.local_metadata(local_path, &ignore).await?;
+            let r_metadata =
+                Self::open_metadata(remote_files.get(*file).unwrap().clone(), state.cipher(file))?;
+            let baseline = Self::load_baseline(&self.global_data, file).await;
+            let plan =
+                Self::diff_local_remote_file(&l_metadata, &r_metadata, baseline.as_ref(), &ignore);
+            let (add_files, del_files, modify_files) = plan.classify(&l_metadata, &r_metadata);
+            result.push(FileMsg {
+                local_dir: state.file_sync.files.get(*file).unwrap().clone(),
+                remote_dir: file.to_string(),
+                status: plan.status(),
+                add: add_files.len() as u32,
+                del: del_files.len() as u32,
+                modify: modify_files.len() as u32,
+                conflict: plan.conflicts.len() as u32,
+                encrypted: state.cipher(file).is_some(),
+                account: state.account(file).to_string(),
+                tag: r_metadata.tag.clone(),
+            });
+        }
+        Ok(ListFileMsg { files: result })
+    }
+
+    /// 同步一个文件夹
+    async fn sync_dir(&mut self, remote_dir: StringMsg, progress: Progress) -> Result<SyncFileDetailMsg> {
+        let state = self.state.lock().await;
+        Self::sync_folder(&self.global_data, &state, &remote_dir.value, &[], progress).await
+    }
+
+    /// 预览同步计划 不做任何修改
+    async fn preview_sync_dir(&mut self, remote_dir: StringMsg) -> Result<SyncPreviewMsg> {
+        let state = self.state.lock().await;
+        let diff = Self::diff_folder(&self.global_data, &state, &remote_dir.value).await?;
+        Ok(SyncPreviewMsg {
+            items: diff.plan.preview(&diff.l_metadata, &diff.r_metadata),
+            ignored: diff.plan.ignored.len() as u32,
+            remote_dir: remote_dir.value,
+        })
+    }
+
+    /// 按预览结果同步一个文件夹 跳过排除的文件
+    async fn apply_sync_dir(&mut self, req: ApplySyncDirMsg, progress: Progress) -> Result<SyncFileDetailMsg> {
+        let state = self.state.lock().await;
+        Self::sync_folder(&self.global_data, &state, &req.remote_dir, &req.exclude, progress).await
+    }
+
+    /// 回收站文件列表 远端路径为空时返回全部
+    async fn list_trash(&mut self, remote_dir: StringMsg) -> Result<SyncTrashMsg> {
+        let state = self.state.lock().await;
+        Ok(SyncTrashMsg {
+            items: state.trash.list(&remote_dir.value),
+        })
+    }
+
+    /// 还原回收站中的文件 本地已存在的文件移入回收站
+    async fn restore_trash(&mut self, id: StringMsg) -> Result<()> {
+        let state = self.state.lock().await;
+        let item = state
+            .trash
+            .list("")
+            .into_iter()
+            .find(|x| x.id == id.value)
+            .ok_or_else(|| ServiceError::NotFound(format!("回收站中不存在{}", id.value)))?;
+        let local_dir = state
+            .file_sync
+            .files
+            .get(&item.remote_dir)
+            .ok_or_else(|| ServiceError::NotFound("本地路径不存在".to_string()))?;
+        let target = local_path(local_dir, &item.file);
+        state
+            .trash
+            .trash(&item.remote_dir, now_millis(), &item.file, &target, TrashReasonEnumMsg::OVERWRITTEN)
+            .await?;
+        state.trash.restore(&item, &target).await?;
+        state.trash.save(&self.global_data).await
+    }
+
+    /// 永久删除回收站中的文件 id为空时清空
+    async fn purge_trash(&mut self, id: StringMsg) -> Result<()> {
+        let state = self.state.lock().await;
+        state.trash.purge(&id.value).await?;
+        state.trash.save(&self.global_data).await
+    }
+
+    /// 远端历史版本列表
+    async fn list_remote_versions(&mut self, req: RemoteVersionReqMsg) -> Result<RemoteVersionsMsg> {
+        let state = self.state.lock().await;
+        let r_metadata = state.remote_metadata(&req.remote_dir).await?;
+        let versions = r_metadata
+            .versions
+            .into_iter()
+            .filter(|x| req.file.is_empty() || x.file == req.file)
+            .collect();
+        Ok(RemoteVersionsMsg { versions })
+    }
+
+    /// 还原远端历史版本 其它设备下次同步时下载
+    async fn restore_remote_version(&mut self, req: RestoreRemoteVersionMsg) -> Result<()> {
+        let state = self.state.lock().await;
+        let storage = state.storage(&req.remote_dir)?;
+        let cipher = state.cipher(&req.remote_dir);
+        let lock = RemoteLock::acquire(storage, &req.remote_dir).await?;
+        let result = lock
+            .hold(storage, async {
+                let mut r_metadata = state.remote_metadata(&req.remote_dir).await?;
+                Self::restore_version(storage, cipher, &mut r_metadata, &req).await?;
+                r_metadata.last_time = r_metadata.files.values().max().copied().unwrap_or_default();
+                Self::update_remote_metadata(&r_metadata, &req.remote_dir, storage, cipher).await
+            })
+            .await;
+        lock.release(storage).await;
+        result
+    }
+
+    /// 同步一个文件夹 结束后发布同步完成事件
+    async fn sync_folder(
+        global_data: &GlobalData,
+        state: &SyncState,
+        remote_dir: &str,
+        exclude: &[String],
+        progress: Progress,
+    ) -> Result<SyncFileDetailMsg> {
+        let result = Self::sync_locked(global_data, state, remote_dir, exclude, progress).await;
+        if let Err(e) = state.trash.save(global_data).await {
+            warn!("保存回收站记录失败: {}", e);
+        }
+        global_data.event_bus().publish(
+            topic::SYNC_FINISHED,
+            &SyncFinishedMsg {
+                remote_dir: remote_dir.to_string(),
+                error: result.as_ref().err().map(|e| e.to_string()),
+            },
+        );
+        result
+    }
+
+    /// 持有远端锁同步 先恢复上次未完成的同步
+    async fn sync_locked(
+        global_data: &GlobalData,
+        state: &SyncState,
+        remote_dir: &str,
+        exclude: &[String],
+        progress: Progress,
+    ) -> Result<SyncFileDetailMsg> {
+        let storage = state.storage(remote_dir)?;
+        let lock = RemoteLock::acquire(storage, remote_dir).await?;
+        let result = lock
+            .hold(storage, async {
+                Self::recover_journal(global_data, state, remote_dir).await?;
+                Self::sync_dir_inner(global_data, state, remote_dir, exclude, progress).await
+            })
+            .await;
+        lock.release(storage).await;
+        result
+    }
+
+    async fn sync_dir_inner(
+        global_data: &GlobalData,
+        state: &SyncState,
+        remote_dir: &str,
+        exclude: &[String],
+        progress: Progress,
+    ) -> Result<SyncFileDetailMsg> {
+        let FolderDiff {
+            local_dir,
+            l_metadata,
+            r_metadata: mut remote_metadata,
+            baseline,
+            mut plan,
+            ignore,
+        } = Self::diff_folder(global_data, state, remote_dir).await?;
+        let storage = state.storage(remote_dir)?;
+        let run_id = now_millis();
+        state.trash.expire(state.config.trash_days).await?;
+        plan.exclude(exclude);
+        let status = plan.status();
+        let (add_files, del_files, modify_files) = plan.classify(&l_metadata, &remote_metadata);
+        let conflict_files = plan.conflicts.clone();
+        let ignored = plan.ignored.len() as u32;
+        Self::resolve_conflicts(
+            local_dir,
+            state.config.conflict_policy,
+            &l_metadata,
+            &remote_metadata,
+            &mut plan,
+            &ignore,
+        )
+        .await?;
+        let mut progress = SyncProgress {
+            progress,
+            current: 0,
+            total: plan.len() as u32,
+            journal: JournalWriter::begin(global_data, remote_dir, run_id, &plan.delete_remote).await?,
+        };
+
+        // 执行相关操作
+        Self::upload_files(
+            &mut remote_metadata,
+            &mut plan.upload,
+            local_dir,
+            remote_dir,
+            state,
+            run_id,
+            &mut progress,
+        )
+        .await?;
+        Self::delete_remote_files(
+            &mut remote_metadata,
+            &plan.delete_remote,
+            remote_dir,
+            state,
+            run_id,
+            &mut progress,
+        )
+        .await?;
+        Self::download_files(
+            &mut remote_metadata,
+            &plan.download,
+            local_dir,
+            remote_dir,
+            state,
+            run_id,
+            &mut progress,
+        )
+        .await?;
+        Self::delete_local_files(
+            &plan.delete_local,
+            local_dir,
+            remote_dir,
+            &state.trash,
+            run_id,
+            &mut progress,
+        )
+        .await?;
+
+        // 补全旧版本远端属性中缺失的hash
+        for (file, hash) in &l_metadata.hashes {
+            if !remote_metadata.hashes.contains_key(file)
+                && remote_metadata.files.get(file) == l_metadata.files.get(file)
+            {
+                remote_metadata.hashes.insert(file.clone(), hash.clone());
+            }
+        }
+
+        // 更新远端文件属性
+        let max = remote_metadata
+            .files
+            .values()
+            .max()
+            .as_deref()
+            .unwrap_or(&0)
+            .clone();
+        remote_metadata.last_time = max;
+        Self::update_remote_metadata(&remote_metadata, remote_dir, storage, state.cipher(remote_dir))
+            .await?;
+        progress.journal.finish().await?;
+        // 记录本次同步结果作为基线
+        Self::save_baseline(global_data, local_dir, remote_dir, &remote_metadata, baseline, exclude, &ignore)
+            .await?;
+
+        // 返回数据
+        Ok(SyncFileDetailMsg {
+            status,
+            add_files,
+            del_files,
+            modify_files,
+            conflict_files,
+            ignored,
+        })
+    }
+
+    /// 新增一个同步文件夹条目
+    async fn add_sync_dir(&mut self, sync: AddSyncDirMsg) -> Result<FileMsg> {
+        let mut state = self.state.lock().await;
+        // 1. 基本校验
+        Self::check_local_dir(&state, &sync.local_dir)?;
+
+        // 2. 构造远端目录
+        let remote_dir =
+            sha256(format!("{}_{}", get_machine_id()?, sync.local_dir).as_bytes()) + "/";
+        let dir = format!("{}{remote_dir}", WEBDAV_SYNC_DIR);
+        let account = match sync.account.is_empty() {
+            true => DEFAULT_ACCOUNT.to_string(),
+            false => sync.account,
+        };
+        let storage = state.account_storage(&account)?;
+        storage.mkdir(&dir).await?;
+
+        // 3. 构造空的文件属性 设定密码时由密码派生密钥加密
+        let cipher = (!sync.passphrase.is_empty())
+            .then(|| SyncCipher::create(&sync.passphrase, sync.encrypt_names));
+        if let Some(cipher) = &cipher {
+            self.global_data.set_secret(cipher_secret(&remote_dir), cipher.key()).await?;
+        }
+        let metadata = RemoteFileMedata {
+            tag: sync.tag.clone(),
+            ..Default::default()
+        };
+        Self::update_remote_metadata(&metadata, &remote_dir, storage, cipher.as_ref()).await?;
+        state
+            .file_sync
+            .files
+            .insert(remote_dir.clone(), sync.local_dir.clone());
+        if !sync.ignore.is_empty() {
+            state.file_sync.ignores.insert(remote_dir.clone(), sync.ignore);
+        }
+        let encrypted = cipher.is_some();
+        if let Some(cipher) = cipher {
+            state.file_sync.keys.insert(remote_dir.clone(), cipher);
+        }
+        state.file_sync.accounts.insert(remote_dir.clone(), account.clone());
+
+        // 返回需要上传的所有文件
+        let ignore = state.ignore_rules(&remote_dir, &sync.local_dir).await;
+        let l_metadata = state.local_metadata(&sync.local_dir, &ignore).await?;
+        let result = FileMsg {
+            local_dir: sync.local_dir,
+            remote_dir,
+            status: FileStatusEnumMsg::UPLOAD.into(),
+            add: l_metadata.files.keys().len() as u32,
+            del: 0,
+            modify: 0,
+            conflict: 0,
+            encrypted,
+            account,
+            tag: sync.tag
+        };
+        Ok(result)
+    }
+
+    /// 对空缺的远端目录新增本地路径
+    async fn add_local_file(&mut self, req: AddLocalForRemoteMsg) -> Result<FileMsg> {
+        let mut state = self.state.lock().await;
+        // 1. 基本校验
+        // 本地校验
+        Self::check_local_dir(&state, &req.local_dir)?;
+        // 暂时取消
+        // 判断本地文件夹是否非空
+        // let mut entries = fs::read_dir(&req.local_dir).await?;
+        // let entry = entries.next_entry().await?;
+        // if entry.is_some() {
+        //     return Err(anyhow!("添加的本地文件夹必须为空文件夹"));
+        // }
+
+        // 获取远端数据 加密时校验密码
+        let account = match req.account.is_empty() {
+            true => state.account(&req.remote_dir).to_string(),
+            false => req.account,
+        };
+        let r_metadata =
+            Self::get_remote_dir_metadatas(state.account_storage(&account)?, &req.remote_dir).await?;
+        let cipher = match &r_metadata.crypto {
+            Some(info) if !req.passphrase.is_empty() => Some(SyncCipher::open(&req.passphrase, info)?),
+            _ => None,
+        };
+        let r_metadata = Self::open_metadata(r_metadata, cipher.as_ref())?;
+        if let Some(cipher) = &cipher {
+            self.global_data.set_secret(cipher_secret(&req.remote_dir), cipher.key()).await?;
+        }
+        let encrypted = cipher.is_some();
+        state
+            .file_sync
+            .files
+            .insert(req.remote_dir.clone(), req.local_dir.clone());
+        if let Some(cipher) = cipher {
+            state.file_sync.keys.insert(req.remote_dir.clone(), cipher);
+        }
+        state.file_sync.accounts.insert(req.remote_dir.clone(), account.clone());
+        let includes: Vec<String> = req.includes.iter().filter_map(|x| normalize_include(x)).collect();
+        if !includes.is_empty() {
+            state.file_sync.includes.insert(req.remote_dir.clone(), includes);
+        }
+        // 只计算选择同步的文件
+        let ignore = state.ignore_rules(&req.remote_dir, &req.local_dir).await;
+        let add = r_metadata.files.keys().filter(|x| !ignore.is_unselected(x)).count();
+        Ok(FileMsg {
+            local_dir: req.local_dir,
+            remote_dir: req.remote_dir,
+            status: FileStatusEnumMsg::DOWNLOAD.into(),
+            add: add as u32,
+            del: 0,
+            modify: 0,
+            conflict: 0,
+            encrypted,
+            account,
+            tag: r_metadata.tag
+        })
+    }
+
+    /// 删除本地路径
+    async fn del_local_dir(&mut self, local_dir: StringMsg) -> Result<()> {
+        let mut state = self.state.lock().await;
+        let remote_dirs: Vec<String> = state
+            .file_sync
+            .files
+            .iter()
+            .filter(|(_, v)| *v == &local_dir.value)
+            .map(|(k, _)| k.clone())
+            .collect();
+        state.watcher.unwatch(&local_dir.value);
+        for remote_dir in remote_dirs {
+            state.file_sync.files.remove(&remote_dir);
+            state.file_sync.ignores.remove(&remote_dir);
+            state.file_sync.includes.remove(&remote_dir);
+            if state.file_sync.keys.remove(&remote_dir).is_some() {
+                self.global_data.del_secret(cipher_secret(&remote_dir)).await?;
+            }
+            state.file_sync.accounts.remove(&remote_dir);
+            Self::del_baseline(&self.global_data, &remote_dir).await?;
+        }
+        Ok(())
+    }
+
+    /// 删除远端路径(会将远端数据一并删除)
+    async fn del_remote_dir(&mut self, remote_dir: StringMsg) -> Result<()> {
+        let mut state = self.state.lock().await;
+        let dir = format!("{}{}", WEBDAV_SYNC_DIR, remote_dir.value);
+        state.storage(&remote_dir.value)?.delete(&dir).await?;
+        if let Some(local_dir) = state.file_sync.files.remove(&remote_dir.value) {
+            state.watcher.unwatch(&local_dir);
+        }
+        state.file_sync.ignores.remove(&remote_dir.value);
+        state.file_sync.includes.remove(&remote_dir.value);
+        if state.file_sync.keys.remove(&remote_dir.value).is_some() {
+            self.global_data.del_secret(cipher_secret(&remote_dir.value)).await?;
+        }
+        state.file_sync.accounts.remove(&remote_dir.value);
+        Self::del_baseline(&self.global_data, &remote_dir.value).await?;
+        Ok(())
+    }
+
+    /// 设定本机选择同步的子文件夹 未选择的子文件夹不下载，也不视为本地删除
+    async fn set_includes(&mut self, req: SyncIncludesMsg) -> Result<()> {
+        let mut state = self.state.lock().await;
+        if !state.file_sync.files.contains_key(&req.remote_dir) {
+            return Err(ServiceError::NotFound(format!("同步文件夹{}不存在", req.remote_dir)).into());
+        }
+        let includes: Vec<String> = req.includes.iter().filter_map(|x| normalize_include(x)).collect();
+        // 上次扫描结果不含未选择的路径 需要全量扫描
+        if let Some(local_dir) = state.file_sync.files.get(&req.remote_dir) {
+            state.watcher.invalidate(local_dir);
+        }
+        match includes.is_empty() {
+            true => state.file_sync.includes.remove(&req.remote_dir),
+            false => state.file_sync.includes.insert(req.remote_dir, includes),
+        };
+        Ok(())
+    }
+
+    /// 获取本机选择同步的子文件夹 为空时同步全部
+    async fn get_includes(&mut self, remote_dir: StringMsg) -> Result<SyncIncludesMsg> {
+        let state = self.state.lock().await;
+        let includes = state
+            .file_sync
+            .includes
+            .get(&remote_dir.value)
+            .map(|x| x.iter().map(|x| x.trim_matches('/').to_string()).collect())
+            .unwrap_or_default();
+        Ok(SyncIncludesMsg {
+            remote_dir: remote_dir.value,
+            includes,
+        })
+    }
+}
+
+impl SyncFileService {
+    /// 获取远端服务的所有文件夹绝对路由（String）及每一项的文件属性
+    /// 列出远端同步文件夹 以/结尾
+    async fn list_remote_dirs(storage: &dyn SyncStorage) -> Result<Vec<String>> {
+        Ok(storage
+            .list_dirs(WEBDAV_SYNC_DIR)
+            .await?
+            .into_iter()
+            // 历史版本文件夹不是同步文件夹
+            .filter(|dir| dir != VERSIONS_DIR.trim_end_matches('/'))
+            .map(|dir| dir + "/")
+            .collect())
+    }
+
+    /// 获取远端同步文件夹及其属性 属性文件版本未变化时使用缓存
+    async fn get_remote_dirs(
+        storage: &dyn SyncStorage,
+        account: &str,
+        cache: &mut MetadataCache,
+    ) -> Result<AHashMap<String, RemoteFileMedata>> {
+        let mut remote_files = AHashMap::new();
+        for dir in Self::list_remote_dirs(storage).await? {
+            let path = format!("{}{}{}", WEBDAV_SYNC_DIR, dir, METADATA_FILE);
+            let version = storage
+                .version(&path)
+                .await
+                .map_err(|x| ServiceError::Remote(format!("无法获取远端文件夹属性文件{}修改时间 {}", dir, x)))?;
+            if let Some(metadata) = cache.get(account, &dir, &version) {
+                remote_files.insert(dir, metadata.clone());
+                continue;
+            }
+            let metadata = Self::get_remote_dir_metadatas(storage, dir.as_str())
+                .await
+                .map_err(|x| ServiceError::Remote(format!("无法获取远端文件夹属性文件{} {}", dir, x)))?;
+            cache.insert(account, &dir, version, metadata.clone());
+            remote_files.insert(dir, metadata);
+        }
+        cache.retain(account, &remote_files);
+        Ok(remote_files)
+    }
+
+    /// 获取远端目录文件属性
+    async fn get_remote_dir_metadatas(storage: &dyn SyncStorage, dir: &str) -> Result<RemoteFileMedata> {
+        let dir = format!("{}{}{}", WEBDAV_SYNC_DIR, dir, METADATA_FILE);
+        let rsp = storage.read(&dir).await?;
+        Ok(serde_json::from_slice(&rsp)?)
+    }
+
+    /// 获取本地目录下 所有文件属性+最新的文件修改时间
+    async fn get_newest_file(
+        dir: &str,
+        hash_cache: &HashCache,
+        ignore: &IgnoreRules,
+    ) -> Result<LocalFileMetadata> {
+        let mut metadata = LocalFileMetadata::default();
+        let mut exists = AHashSet::new();
+        Self::scan_local(dir, Path::new(dir), hash_cache, ignore, &mut metadata, &mut exists).await?;
+        hash_cache.prune(dir, &exists);
+        metadata.last_time = metadata.files.values().max().copied().unwrap_or(0);
+        Ok(metadata)
+    }
+
+    /// 扫描本地目录下的路径 结果记录到文件属性中
+    /// 被忽略的文件夹不再遍历其下内容
+    async fn scan_local(
+        dir: &str,
+        start: &Path,
+        hash_cache: &HashCache,
+        ignore: &IgnoreRules,
+        result: &mut LocalFileMetadata,
+        exists: &mut AHashSet<String>,
+    ) -> Result<()> {
+        let mut ignored = Vec::new();
+        let walker = walkdir::WalkDir::new(start).into_iter().filter_entry(|entry| {
+            let path = sync_path(dir, entry.path(), entry.file_type().is_dir());
+            if ignore.is_ignored(&path) {
+                // 未选择同步的路径不计入忽略
+                if !ignore.is_unselected(&path) {
+                    ignored.push(path);
+                }
+                return false;
+            }
+            true
+        });
+        for entry in walker {
+            if entry.is_err() {
+                continue;
+            }
+            let entry = entry?;
+            if entry.path().is_symlink() || is_temp_file(entry.path()) {
+                continue;
+            }
+
+            let path = sync_path(dir, entry.path(), entry.path().is_dir());
+            if path == "/" {
+                continue;
+            }
+            let metadata = metadata(entry.path()).await?;
+            let max = metadata.modified()?.duration_since(UNIX_EPOCH)?.as_millis();
+            if metadata.is_file() {
+                let hash = hash_cache.hash(entry.path(), metadata.len(), max).await?;
+                result.hashes.insert(path.clone(), hash);
+                result.sizes.insert(path.clone(), metadata.len());
+                exists.insert(entry.path().to_string_lossy().to_string());
+            }
+            result.files.insert(path, max);
+        }
+        result.ignored.extend(ignored);
+        Ok(())
+    }
+
+    /// 上传文件
+    async fn upload_files(
+        remote_metadata: &mut RemoteFileMedata,
+        local_files: &mut [String],
+        local_dir: &str,
+        remote_dir: &str,
+        state: &SyncState,
+        run_id: u64,
+        progress: &mut SyncProgress,
+    ) -> Result<()> {
+        let storage = state.storage(remote_dir)?;
+        let cipher = state.cipher(remote_dir);
+        // 排序，这样文件依赖的文件夹路径一定存在于其之前
+        local_files.sort();
+        // 1. 新建文件夹
+        for file in local_files.iter().filter(|x| x.ends_with('/')) {
+            storage.mkdir(&remote_path(cipher, remote_dir, file)).await?;
+            progress.step(file);
+            // 更新远端文件属性
+            let metadata = metadata(local_path(local_dir, file)).await?;
+            let modified = metadata.modified()?.duration_since(UNIX_EPOCH)?.as_millis();
+            remote_metadata.files.insert(file.clone(), modified);
+            progress.journal.uploaded(file, modified, None, None).await?;
+        }
+        // 2. 并行上传文件 开启历史版本时覆盖前先移入历史版本
+        let version_time = state.config.remote_versions.then_some(run_id);
+        let overwrite: AHashSet<&String> = local_files
+            .iter()
+            .filter(|x| remote_metadata.files.contains_key(*x))
+            .collect();
+        let uploads: Vec<_> = local_files
+            .iter()
+            .filter(|x| !x.ends_with('/'))
+            .map(|file| {
+                let version = version_time.filter(|_| overwrite.contains(file));
+                Self::upload_file(file, version, local_dir, remote_dir, state).boxed()
+            })
+            .collect();
+        let mut uploads = futures::stream::iter(uploads).buffer_unordered(MAX_TRANSFERS);
+        let mut versions = Vec::new();
+        let mut result = Ok(());
+        while let Some(uploaded) = uploads.next().await {
+            let (file, modified, hash, size) = match uploaded {
+                Ok(x) => x,
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            };
+            progress.step(file);
+            if let Some(time) = version_time.filter(|_| overwrite.contains(file)) {
+                let records = Self::version_records(remote_metadata, file, time);
+                progress.journal.versions(&records);
+                versions.extend(records);
+            }
+            // 更新远端文件属性
+            remote_metadata.files.insert(file.clone(), modified);
+            remote_metadata.hashes.insert(file.clone(), hash.clone());
+            remote_metadata.sizes.insert(file.clone(), size);
+            if let Err(e) = progress.journal.uploaded(file, modified, Some(hash), Some(size)).await {
+                result = Err(e);
+                break;
+            }
+        }
+        drop(uploads);
+        Self::add_remote_versions(storage, cipher, remote_metadata, remote_dir, versions).await;
+        result
+    }
+
+    /// 以流的方式上传单个文件 加密文件夹上传前加密 返回 (文件，修改时间，hash，大小)
+    /// 先上传到临时文件，完成后再替换远端文件，上传失败时远端文件保持不变
+    /// version不为空时替换前先将远端文件移入该时间的历史版本
+    async fn upload_file<'a>(
+        file: &'a String,
+        version: Option<u64>,
+        local_dir: &str,
+        remote_dir: &str,
+        state: &SyncState,
+    ) -> Result<(&'a String, u128, String, u64)> {
+        let storage = state.storage(remote_dir)?;
+        let cipher = state.cipher(remote_dir);
+        let path = local_path(local_dir, file);
+        let metadata = metadata(&path).await?;
+        let modified = metadata.modified()?.duration_since(UNIX_EPOCH)?.as_millis();
+        let hash = state.hash_cache.hash(&path, metadata.len(), modified).await?;
+        let local_file = File::open(&path).await?;
+        let data: ByteStream = match cipher {
+            Some(cipher) => Box::pin(cipher.encrypt_stream(local_file)?),
+            None => Box::pin(file_stream(local_file)),
+        };
+        let data = state.upload_limit.limit(data);
+        let target = remote_path(cipher, remote_dir, file);
+        let temp = format!("{}{}", target, TEMP_SUFFIX);
+        if let Err(e) = storage.put(&temp, data).await {
+            let _ = storage.delete(&temp).await;
+            return Err(e);
+        }
+        if let Some(time) = version {
+            Self::move_to_version(storage, cipher, remote_dir, file, time).await?;
+        }
+        storage.mv(&temp, &target).await?;
+        Ok((file, modified, hash, metadata.len()))
+    }
+
+    /// 删除远程文件
+    /// 开启历史版本时移入历史版本而不是直接删除
+    async fn delete_remote_files(
+        remote_metadata: &mut RemoteFileMedata,
+        del_files: &[String],
+        remote_dir: &str,
+        state: &SyncState,
+        run_id: u64,
+        progress: &mut SyncProgress,
+    ) -> Result<()> {
+        let storage = state.storage(remote_dir)?;
+        let cipher = state.cipher(remote_dir);
+        let mut versions = Vec::new();
+        for file in del_files {
+            if state.config.remote_versions {
+                let records = Self::version_records(remote_metadata, file, run_id);
+                Self::move_to_version(storage, cipher, remote_dir, file, run_id).await?;
+                progress.journal.versions(&records);
+                versions.extend(records);
+            } else {
+                storage.delete(&remote_path(cipher, remote_dir, file)).await?;
+            }
+            Self::remove_remote_entry(remote_metadata, file);
+            progress.journal.deleted(file).await?;
+            progress.step(file);
+        }
+        Self::add_remote_versions(storage, cipher, remote_metadata, remote_dir, versions).await;
+        Ok(())
+    }
+
+    /// 移除远端文件属性 文件夹同时移除其下文件
+    fn remove_remote_entry(remote_metadata: &mut RemoteFileMedata, file: &str) {
+        remote_metadata.files.remove(file);
+        remote_metadata.hashes.remove(file);
+        remote_metadata.sizes.remove(file);
+        if file.ends_with('/') {
+            remote_metadata.files.retain(|k, _| !k.starts_with(file));
+            remote_metadata.hashes.retain(|k, _| !k.starts_with(file));
+            remote_metadata.sizes.retain(|k, _| !k.starts_with(file));
+        }
+    }
+
+    /// 下载远端文件
+    async fn download_files(
+        remote_metadata: &mut RemoteFileMedata,
+        add_files: &[String],
+        local_dir: &str,
+        remote_dir: &str,
+        state: &SyncState,
+        run_id: u64,
+        progress: &mut SyncProgress,
+    ) -> Result<()> {
+        // 1. 创建文件夹
+        for file in add_files {
+            if !file.ends_with('/') {
+                continue;
+            }
+            let path = local_path(local_dir, file);
+            if !path.exists() {
+                create_dir_all(path).await?;
+            }
+            progress.step(file);
+        }
+        // 2. 并行下载文件
+        let r_metadata = &*remote_metadata;
+        let downloads: Vec<_> = add_files
+            .iter()
+            .filter(|x| !x.ends_with('/'))
+            .map(|file| {
+                let hash = r_metadata.hashes.get(file);
+                Self::download_file(file, hash, local_dir, remote_dir, state, run_id).boxed()
+            })
+            .collect();
+        let mut downloads = futures::stream::iter(downloads).buffer_unordered(MAX_TRANSFERS);
+        while let Some(result) = downloads.next().await {
+            let (file, path, size, hash) = result?;
+            // 已知内容，按远端修改时间记录hash 避免下次重新计算
+            let time = *r_metadata.files.get(file).unwrap();
+            state.hash_cache.insert(path.to_string_lossy().to_string(), size, time, hash);
+            progress.step(file);
+        }
+        drop(downloads);
+        // 3. 修改时间戳
+        for file in add_files {
+            let time = *remote_metadata.files.get(file).unwrap();
+            let path = local_path(local_dir, file);
+            let system_time = SystemTime::UNIX_EPOCH.add(Duration::from_millis(time as u64));
+            filetime::set_file_mtime(path, FileTime::from(system_time))?
+        }
+
+        Ok(())
+    }
+
+    /// 以流的方式下载单个文件 先写入临时文件，校验通过后重命名到目标路径
+    /// 被覆盖的本地文件移入回收站 返回 (文件，本地路径，大小，hash)
+    async fn download_file<'a>(
+        file: &'a String,
+        expect_hash: Option<&String>,
+        local_dir: &str,
+        remote_dir: &str,
+        state: &SyncState,
+        run_id: u64,
+    ) -> Result<(&'a String, PathBuf, u64, String)> {
+        let path = local_path(local_dir, file);
+        let temp = temp_path(&path);
+        // 还原的历史版本可能缺少上级文件夹
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent).await?;
+        }
+        let cipher = state.cipher(remote_dir);
+        let result = Self::download_to(
+            &remote_path(cipher, remote_dir, file),
+            &temp,
+            state.storage(remote_dir)?,
+            cipher,
+            &state.download_limit,
+        )
+        .await
+        .and_then(|(size, hash)| match expect_hash {
+            Some(expect) if expect != &hash => {
+                Err(ServiceError::Remote(format!("文件{}校验失败", file)).into())
+            }
+            _ => Ok((size, hash)),
+        });
+        match result {
+            Ok((size, hash)) => {
+                state
+                    .trash
+                    .trash(remote_dir, run_id, file, &path, TrashReasonEnumMsg::OVERWRITTEN)
+                    .await?;
+                tokio::fs::rename(&temp, &path).await?;
+                Ok((file, path, size, hash))
+            }
+            Err(e) => {
+                let _ = tokio::fs::remove_file(&temp).await;
+                Err(e)
+            }
+        }
+    }
+
+    /// 将远端文件写入本地路径 加密文件夹边下载边解密 返回 (大小，hash)
+    async fn download_to(
+        remote_file: &str,
+        path: &Path,
+        storage: &dyn SyncStorage,
+        cipher: Option<&SyncCipher>,
+        limit: &RateLimiter,
+    ) -> Result<(u64, String)> {
+        let mut stream = storage.get(remote_file).await?;
+        let mut local_file = File::create(path).await?;
+        let mut decryptor = cipher.map(|x| x.decryptor()).transpose()?;
+        let mut sha256 = Sha256::new();
+        let mut size = 0;
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            limit.consume(chunk.len()).await;
+            let chunk = match decryptor.as_mut() {
+                Some(decryptor) => Bytes::from(decryptor.push(&chunk)?),
+                None => chunk,
+            };
+            sha256.update(&chunk);
+            size += chunk.len() as u64;
+            local_file.write_all(&chunk).await?;
+        }
+        if let Some(decryptor) = decryptor {
+            let chunk = decryptor.finish()?;
+            sha256.update(&chunk);
+            size += chunk.len() as u64;
+            local_file.write_all(&chunk).await?;
+        }
+        local_file.flush().await?;
+        local_file.sync_all().await?;
+        Ok((size, format!("{:x}", sha256.finalize())))
+    }
+
+    /// 删除本地文件 移入回收站
+    async fn delete_local_files(
+        del_files: &[String],
+        local_dir: &str,
+        remote_dir: &str,
+        trash: &SyncTrash,
+        run_id: u64,
+        progress: &mut SyncProgress,
+    ) -> Result<()> {
+        for file in del_files {
+            let path = local_path(local_dir, file);
+            trash
+                .trash(remote_dir, run_id, file, &path, TrashReasonEnumMsg::DELETED)
+                .await?;
+            progress.step(file);
+        }
+        Ok(())
+    }
+
+    /// 更新远端属性文件 加密文件夹仅保留tag及最新修改时间为明文
+    /// 先写入临时文件再移动覆盖，避免中断时属性文件不完整
+    async fn update_remote_metadata(
+        remote_file_medata: &RemoteFileMedata,
+        remote_dir: &str,
+        storage: &dyn SyncStorage,
+        cipher: Option<&SyncCipher>,
+    ) -> Result<()> {
+        let dir = format!("{}{}{}", WEBDAV_SYNC_DIR, remote_dir, METADATA_FILE);
+        let temp = format!("{}{}", dir, TEMP_SUFFIX);
+        let content = match cipher {
+            Some(cipher) => serde_json::to_vec(&RemoteFileMedata {
+                tag: remote_file_medata.tag.clone(),
+                last_time: remote_file_medata.last_time,
+                crypto: Some(cipher.info()),
+                sealed: Some(cipher.seal(&serde_json::to_vec(remote_file_medata)?)?),
+                ..Default::default()
+            })?,
+            None => serde_json::to_vec(remote_file_medata)?,
+        };
+        storage.write(&temp, content).await?;
+        storage.mv(&temp, &dir).await?;
+        Ok(())
+    }
+
+    /// 解密远端文件属性 密钥与远端加密信息不一致时返回错误，避免写坏远端数据
+    fn open_metadata(
+        metadata: RemoteFileMedata,
+        cipher: Option<&SyncCipher>,
+    ) -> Result<RemoteFileMedata> {
+        match (&metadata.crypto, cipher) {
+            (None, None) => Ok(metadata),
+            (Some(_), None) => {
+                Err(ServiceError::InvalidInput("文件夹已加密，需要密码".to_string()).into())
+            }
+            (None, Some(_)) => {
+                Err(ServiceError::InvalidInput("远端文件夹未加密".to_string()).into())
+            }
+            (Some(info), Some(cipher)) => {
+                cipher.verify(info)?;
+                let sealed = metadata.sealed.as_deref().unwrap_or_default();
+                let mut opened: RemoteFileMedata = serde_json::from_slice(&cipher.unseal(sealed)?)?;
+                opened.crypto = metadata.crypto;
+                Ok(opened)
+            }
+        }
+    }
+
+    /// 新增本地文件夹校验
+    fn check_local_dir(state: &SyncState, local_dir: &str) -> Result<()> {
+        // 1. 基本校验
+        let add_path = Path::new(local_dir);
+        if !add_path.exists() || !add_path.is_dir() {
+            return Err(ServiceError::InvalidInput(format!("{}路径不存在或非目录", local_dir)).into());
+        }
+
+        // 2. 不允许存在路径包含关系
+        for path in state.file_sync.files.values() {
+            if path.starts_with(local_dir) || local_dir.starts_with(path) {
+                return Err(ServiceError::InvalidInput(format!("设定路径存在包含关系: 已存在路径 {}", path)).into());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// 按块读取文件的流
+fn file_stream(file: File) -> impl Stream<Item = std::io::Result<Bytes>> + Send + 'static {
+    futures::stream::try_unfold(file, |mut file| async move {
+        let mut buf = vec![0; CHUNK_SIZE];
+        let n = file.read(&mut buf).await?;
+        if n == 0 {
+            return Ok(None);
+        }
+        buf.truncate(n);
+        Ok::<_, std::io::Error>(Some((Bytes::from(buf), file)))
+    })
+}
+
+/// 下载临时文件路径 与目标文件同目录，保证重命名为原子操作
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|x| x.to_string_lossy().to_string())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}{}", name, TEMP_SUFFIX))
+}
+
+/// 是否为下载中的临时文件
+fn is_temp_file(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|x| x.to_string_lossy().ends_with(TEMP_SUFFIX))
+}
+
+/// 本地路径对应的同步路径 文件夹以/结尾
+fn sync_path(dir: &str, path: &Path, is_dir: bool) -> String {
+    let mut path = path
+        .to_str()
+        .unwrap()
+        .strip_prefix(dir)
+        .unwrap()
+        .to_string();
+    if is_dir {
+        path += "/";
+    }
+    path.replace(r"\", "/")
+}
+
+/// 当前时间 ms
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_millis() as u64)
+        .unwrap_or_default()
+}
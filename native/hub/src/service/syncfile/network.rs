@@ -0,0 +1,82 @@
+/// 网络状态
+/// linux下通过NetworkManager判断是否按流量计费，其它平台视为不计费
+#[derive(Clone, Default)]
+pub(super) struct NetworkMonitor {
+    #[cfg(target_os = "linux")]
+    bus: std::sync::Arc<tokio::sync::OnceCell<Option<linux::SystemBus>>>,
+}
+
+impl NetworkMonitor {
+    /// 当前连接是否按流量计费 无法判断时视为不计费
+    pub(super) async fn is_metered(&self) -> bool {
+        #[cfg(target_os = "linux")]
+        {
+            let bus = self
+                .bus
+                .get_or_init(|| async {
+                    linux::SystemBus::connect()
+                        .inspect_err(|e| log::warn!("无法连接系统D-Bus，不判断按流量计费网络: {}", e))
+                        .ok()
+                })
+                .await;
+            if let Some(bus) = bus {
+                return bus
+                    .is_metered()
+                    .await
+                    .inspect_err(|e| log::warn!("获取网络计费状态失败: {}", e))
+                    .unwrap_or(false);
+            }
+        }
+        false
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use crate::dbus::network_manager::OrgFreedesktopNetworkManager;
+    use anyhow::Result;
+    use dbus::nonblock::{Proxy, SyncConnection};
+    use dbus_tokio::connection;
+    use log::error;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::task::JoinHandle;
+
+    // NMMetered 是/推测为是
+    const NM_METERED_YES: u32 = 1;
+    const NM_METERED_GUESS_YES: u32 = 3;
+
+    /// 系统总线连接 NetworkManager位于系统总线
+    pub(super) struct SystemBus {
+        conn: Arc<SyncConnection>,
+        resource: JoinHandle<()>,
+    }
+
+    impl SystemBus {
+        pub(super) fn connect() -> Result<Self> {
+            let (resource, conn) = connection::new_system_sync()?;
+            let resource = tokio::spawn(async {
+                let err = resource.await;
+                error!("Lost connection to D-Bus: {}", err);
+            });
+            Ok(Self { conn, resource })
+        }
+
+        pub(super) async fn is_metered(&self) -> Result<bool> {
+            let proxy = Proxy::new(
+                "org.freedesktop.NetworkManager",
+                "/org/freedesktop/NetworkManager",
+                Duration::from_secs(5),
+                self.conn.clone(),
+            );
+            let metered = proxy.metered().await?;
+            Ok(matches!(metered, NM_METERED_YES | NM_METERED_GUESS_YES))
+        }
+    }
+
+    impl Drop for SystemBus {
+        fn drop(&mut self) {
+            self.resource.abort();
+        }
+    }
+}
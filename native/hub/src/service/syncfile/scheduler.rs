@@ -0,0 +1,233 @@
+use crate::common::error::ServiceError;
+use crate::common::global_data::GlobalData;
+use crate::messages::syncfile::{SyncRunDirMsg, SyncRunMsg};
+use crate::service::service::Progress;
+use crate::service::syncfile::network::NetworkMonitor;
+use crate::service::syncfile::secrets::SyncSecrets;
+use crate::service::syncfile::{now_millis, storage, AccountInfo, SyncFileService, SyncState};
+use ahash::{AHashMap, AHashSet};
+use anyhow::Result;
+use log::{info, warn};
+use std::collections::VecDeque;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::task::AbortHandle;
+
+// 保留的同步记录数量
+const MAX_RUNS: usize = 50;
+// 失败时间隔最多翻倍次数
+const MAX_BACKOFF: u32 = 4;
+
+/// 定时同步
+/// 按设定间隔同步所有已映射的文件夹，失败时逐次延长间隔
+pub(super) struct SyncScheduler {
+    global_data: GlobalData,
+    state: Arc<Mutex<SyncState>>,
+    accounts: Arc<RwLock<AHashMap<String, AccountInfo>>>,
+    // 同步记录 最新的在前
+    runs: Arc<RwLock<VecDeque<SyncRunMsg>>>,
+    // 网络状态
+    network: NetworkMonitor,
+    // 账户密码及文件夹密钥
+    secrets: SyncSecrets,
+    // 定时任务
+    task: Option<AbortHandle>,
+}
+
+impl SyncScheduler {
+    pub(super) fn new(
+        global_data: GlobalData,
+        state: Arc<Mutex<SyncState>>,
+        accounts: Arc<RwLock<AHashMap<String, AccountInfo>>>,
+        secrets: SyncSecrets,
+    ) -> Self {
+        Self {
+            global_data,
+            state,
+            accounts,
+            runs: Arc::new(RwLock::new(VecDeque::new())),
+            network: NetworkMonitor::default(),
+            secrets,
+            task: None,
+        }
+    }
+
+    /// 同步记录
+    pub(super) fn runs(&self) -> Arc<RwLock<VecDeque<SyncRunMsg>>> {
+        self.runs.clone()
+    }
+
+    /// 按间隔(分钟)重新启动定时同步 为0时停止
+    pub(super) fn start(&mut self, minutes: u32) {
+        self.stop();
+        if minutes == 0 {
+            return;
+        }
+        let interval = Duration::from_secs(minutes as u64 * 60);
+        let global_data = self.global_data.clone();
+        let state = self.state.clone();
+        let accounts = self.accounts.clone();
+        let runs = self.runs.clone();
+        let network = self.network.clone();
+        let secrets = self.secrets.clone();
+        let handle = tokio::spawn(async move {
+            let mut failures = 0;
+            loop {
+                tokio::time::sleep(interval * 2u32.pow(failures)).await;
+                secrets.load().await;
+                let run = match state.try_lock() {
+                    Ok(mut state) => {
+                        if state.config.skip_metered && network.is_metered().await {
+                            Self::skipped("当前网络按流量计费，跳过本次定时同步")
+                        } else {
+                            Self::run(&global_data, &mut state, &accounts).await
+                        }
+                    }
+                    Err(_) => Self::skipped("同步操作进行中，跳过本次定时同步"),
+                };
+                if !run.skipped {
+                    let failed = run.error.is_some() || run.dirs.iter().any(|x| x.error.is_some());
+                    failures = if failed { (failures + 1).min(MAX_BACKOFF) } else { 0 };
+                }
+                let mut records = runs.write().unwrap();
+                records.push_front(run);
+                records.truncate(MAX_RUNS);
+            }
+        });
+        self.task = Some(handle.abort_handle());
+    }
+
+    /// 停止定时同步
+    pub(super) fn stop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+    }
+
+    /// 跳过本次同步的记录
+    fn skipped(reason: &str) -> SyncRunMsg {
+        info!("{}", reason);
+        let now = now_millis();
+        SyncRunMsg {
+            start_time: now,
+            end_time: now,
+            skipped: true,
+            reason: Some(reason.to_string()),
+            error: None,
+            dirs: Vec::new(),
+        }
+    }
+
+    /// 执行一次同步
+    async fn run(
+        global_data: &GlobalData,
+        state: &mut SyncState,
+        accounts: &RwLock<AHashMap<String, AccountInfo>>,
+    ) -> SyncRunMsg {
+        let start_time = now_millis();
+        let mut dirs = Vec::new();
+        let result = Self::sync_all(global_data, state, accounts, &mut dirs).await;
+        if let Err(e) = &result {
+            warn!("定时同步失败: {}", e);
+        }
+        SyncRunMsg {
+            start_time,
+            end_time: now_millis(),
+            skipped: false,
+            reason: None,
+            error: result.err().map(|e| e.to_string()),
+            dirs,
+        }
+    }
+
+    /// 同步本地与远端都存在的文件夹
+    /// 账户连接失败时记录为其下各文件夹的失败原因
+    async fn sync_all(
+        global_data: &GlobalData,
+        state: &mut SyncState,
+        accounts: &RwLock<AHashMap<String, AccountInfo>>,
+        dirs: &mut Vec<SyncRunDirMsg>,
+    ) -> Result<()> {
+        let accounts = accounts.read().unwrap().clone();
+        if accounts.is_empty() {
+            return Err(ServiceError::NotLoggedIn("无账户信息，请登录".to_string()).into());
+        }
+        let bound: AHashSet<String> = state
+            .file_sync
+            .files
+            .keys()
+            .map(|x| state.account(x).to_string())
+            .collect();
+        let mut remote_dirs = AHashSet::new();
+        for account in bound {
+            match Self::account_dirs(state, &accounts, &account).await {
+                Ok(x) => remote_dirs.extend(x),
+                Err(e) => {
+                    warn!("账户{}连接失败: {}", account, e);
+                    for (remote_dir, local_dir) in state.file_sync.files.iter() {
+                        if state.account(remote_dir) != account {
+                            continue;
+                        }
+                        dirs.push(SyncRunDirMsg {
+                            local_dir: local_dir.clone(),
+                            remote_dir: remote_dir.clone(),
+                            add: 0,
+                            del: 0,
+                            modify: 0,
+                            error: Some(format!("账户{}连接失败: {}", account, e)),
+                        });
+                    }
+                }
+            }
+        }
+        for (remote_dir, local_dir) in state.file_sync.files.iter() {
+            if !remote_dirs.contains(remote_dir) {
+                continue;
+            }
+            let result =
+                SyncFileService::sync_folder(global_data, state, remote_dir, &[], Progress::ignore())
+                    .await;
+            dirs.push(match result {
+                Ok(detail) => SyncRunDirMsg {
+                    local_dir: local_dir.clone(),
+                    remote_dir: remote_dir.clone(),
+                    add: detail.add_files.len() as u32,
+                    del: detail.del_files.len() as u32,
+                    modify: detail.modify_files.len() as u32,
+                    error: None,
+                },
+                Err(e) => SyncRunDirMsg {
+                    local_dir: local_dir.clone(),
+                    remote_dir: remote_dir.clone(),
+                    add: 0,
+                    del: 0,
+                    modify: 0,
+                    error: Some(e.to_string()),
+                },
+            });
+        }
+        Ok(())
+    }
+
+    /// 连接账户 返回账户下的远端文件夹
+    async fn account_dirs(
+        state: &mut SyncState,
+        accounts: &AHashMap<String, AccountInfo>,
+        account: &str,
+    ) -> Result<Vec<String>> {
+        if !state.storages.contains_key(account) {
+            let info = accounts
+                .get(account)
+                .ok_or_else(|| ServiceError::NotFound(format!("账户{}不存在", account)))?;
+            state.storages.insert(account.to_string(), storage::connect(info).await?);
+        }
+        SyncFileService::list_remote_dirs(state.account_storage(account)?).await
+    }
+}
+
+impl Drop for SyncScheduler {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -0,0 +1,68 @@
+use crate::common::global_data::GlobalData;
+use crate::service::syncfile::account::account_secret;
+use crate::service::syncfile::{AccountInfo, SyncState};
+use ahash::AHashMap;
+use log::warn;
+use std::sync::{Arc, RwLock};
+use tokio::sync::{Mutex, OnceCell};
+
+/// 账户密码及文件夹密钥
+/// 首次使用时从系统密钥服务读取，避免创建服务时等待密钥服务解锁阻塞其它请求
+#[derive(Clone)]
+pub(super) struct SyncSecrets {
+    global_data: GlobalData,
+    accounts: Arc<RwLock<AHashMap<String, AccountInfo>>>,
+    state: Arc<Mutex<SyncState>>,
+    loaded: Arc<OnceCell<()>>,
+}
+
+impl SyncSecrets {
+    pub(super) fn new(
+        global_data: GlobalData,
+        accounts: Arc<RwLock<AHashMap<String, AccountInfo>>>,
+        state: Arc<Mutex<SyncState>>,
+    ) -> Self {
+        Self {
+            global_data,
+            accounts,
+            state,
+            loaded: Arc::new(OnceCell::new()),
+        }
+    }
+
+    /// 读取密钥 只执行一次，中途取消时下次使用重新读取
+    /// 密钥丢失时保持为空，连接或同步时提示重新填写
+    pub(super) async fn load(&self) {
+        self.loaded
+            .get_or_init(|| async {
+                self.load_passwords().await;
+                let mut state = self.state.lock().await;
+                for (remote_dir, cipher) in state.file_sync.keys.iter_mut() {
+                    if cipher.key().is_empty() {
+                        cipher.load_key(&self.global_data, remote_dir).await;
+                    }
+                }
+            })
+            .await;
+    }
+
+    async fn load_passwords(&self) {
+        let mut accounts = self.accounts.read().unwrap().clone();
+        for (name, account) in accounts.iter_mut() {
+            let secret = account_secret(name);
+            match self.global_data.migrate_secret(secret, &mut account.legacy_passwd).await {
+                Some(passwd) => account.passwd = passwd,
+                None => warn!("账户{}的密码丢失，需重新填写", name),
+            }
+        }
+        // 读取期间重新填写过的账户不覆盖
+        let mut current = self.accounts.write().unwrap();
+        for (name, account) in accounts {
+            if let Some(x) = current.get_mut(&name)
+                && x.passwd.is_empty()
+            {
+                *x = account;
+            }
+        }
+    }
+}
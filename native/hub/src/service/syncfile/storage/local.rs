@@ -0,0 +1,119 @@
+use crate::common::error::ServiceError;
+use crate::service::syncfile::file_stream;
+use crate::service::syncfile::storage::{ByteStream, SyncStorage};
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::StreamExt;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tokio::fs::{
+    copy, create_dir_all, metadata, read, read_dir, remove_dir_all, remove_file, rename, write,
+    File,
+};
+use tokio::io::AsyncWriteExt;
+
+/// 本地文件夹存储 用于挂载的网络共享等
+pub(super) struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    /// 校验根目录
+    pub(super) async fn connect(root: &str) -> Result<Self> {
+        let root = PathBuf::from(root);
+        if !metadata(&root).await.is_ok_and(|x| x.is_dir()) {
+            return Err(ServiceError::InvalidInput(format!("{}路径不存在或非目录", root.display())).into());
+        }
+        Ok(Self { root })
+    }
+
+    /// 存储路径对应的本地路径
+    fn path(&self, path: &str) -> PathBuf {
+        self.root.join(path.trim_start_matches('/'))
+    }
+}
+
+#[async_trait]
+impl SyncStorage for LocalStorage {
+    async fn list_dirs(&self, dir: &str) -> Result<Vec<String>> {
+        let mut entries = read_dir(self.path(dir)).await?;
+        let mut dirs = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            if entry.file_type().await?.is_dir() {
+                dirs.push(entry.file_name().to_string_lossy().to_string());
+            }
+        }
+        Ok(dirs)
+    }
+
+    async fn version(&self, path: &str) -> Result<String> {
+        let meta = metadata(self.path(path)).await?;
+        let mtime = meta.modified()?.duration_since(UNIX_EPOCH)?.as_nanos();
+        Ok(format!("{}-{}", mtime, meta.len()))
+    }
+
+    async fn read(&self, path: &str) -> Result<Vec<u8>> {
+        Ok(read(self.path(path)).await?)
+    }
+
+    async fn write(&self, path: &str, data: Vec<u8>) -> Result<()> {
+        write(self.path(path), data).await?;
+        Ok(())
+    }
+
+    async fn put(&self, path: &str, mut data: ByteStream) -> Result<()> {
+        let mut file = File::create(self.path(path)).await?;
+        while let Some(chunk) = data.next().await {
+            file.write_all(&chunk?).await?;
+        }
+        file.flush().await?;
+        Ok(())
+    }
+
+    async fn get(&self, path: &str) -> Result<ByteStream> {
+        let file = File::open(self.path(path)).await?;
+        Ok(Box::pin(file_stream(file)))
+    }
+
+    async fn delete(&self, path: &str) -> Result<()> {
+        let path = self.path(path);
+        if metadata(&path).await?.is_dir() {
+            remove_dir_all(path).await?;
+        } else {
+            remove_file(path).await?;
+        }
+        Ok(())
+    }
+
+    async fn mkdir(&self, path: &str) -> Result<()> {
+        create_dir_all(self.path(path)).await?;
+        Ok(())
+    }
+
+    async fn mv(&self, from: &str, to: &str) -> Result<()> {
+        rename(self.path(from), self.path(to)).await?;
+        Ok(())
+    }
+
+    async fn cp(&self, from: &str, to: &str) -> Result<()> {
+        copy_path(&self.path(from), &self.path(to)).await
+    }
+}
+
+/// 复制文件或文件夹
+async fn copy_path(from: &Path, to: &Path) -> Result<()> {
+    if !metadata(from).await?.is_dir() {
+        copy(from, to).await?;
+        return Ok(());
+    }
+    for entry in walkdir::WalkDir::new(from) {
+        let entry = entry?;
+        let target = to.join(entry.path().strip_prefix(from)?);
+        if entry.file_type().is_dir() {
+            create_dir_all(&target).await?;
+        } else {
+            copy(entry.path(), &target).await?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,78 @@
+mod local;
+mod webdav;
+
+use crate::common::WEBDAV_SYNC_DIR;
+use crate::service::syncfile::storage::local::LocalStorage;
+use crate::service::syncfile::storage::webdav::WebDavStorage;
+use crate::service::syncfile::AccountInfo;
+use anyhow::Result;
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::Stream;
+use std::pin::Pin;
+
+/// 本地文件夹存储的地址前缀
+const LOCAL_SCHEME: &str = "file://";
+
+/// 上传下载的文件流
+pub(super) type ByteStream = Pin<Box<dyn Stream<Item = std::io::Result<Bytes>> + Send>>;
+
+/// 同步文件的远端存储
+/// 路径均为以/开头的绝对路径，文件夹以/结尾
+#[async_trait]
+pub(super) trait SyncStorage: Send + Sync {
+    /// 列出文件夹下的子文件夹名称
+    async fn list_dirs(&self, dir: &str) -> Result<Vec<String>>;
+
+    /// 文件版本标识 etag或修改时间+大小，内容变化时随之变化
+    async fn version(&self, path: &str) -> Result<String>;
+
+    /// 读取文件全部内容 用于属性文件
+    async fn read(&self, path: &str) -> Result<Vec<u8>>;
+
+    /// 写入文件全部内容 用于属性文件
+    async fn write(&self, path: &str, data: Vec<u8>) -> Result<()>;
+
+    /// 以流的方式上传文件
+    async fn put(&self, path: &str, data: ByteStream) -> Result<()>;
+
+    /// 以流的方式下载文件
+    async fn get(&self, path: &str) -> Result<ByteStream>;
+
+    /// 删除文件或文件夹
+    async fn delete(&self, path: &str) -> Result<()>;
+
+    /// 新建文件夹
+    async fn mkdir(&self, path: &str) -> Result<()>;
+
+    /// 移动文件或文件夹
+    async fn mv(&self, from: &str, to: &str) -> Result<()>;
+
+    /// 复制文件或文件夹
+    async fn cp(&self, from: &str, to: &str) -> Result<()>;
+}
+
+/// 按账户地址连接存储 并保证同步根目录存在
+pub(super) async fn connect(account: &AccountInfo) -> Result<Box<dyn SyncStorage>> {
+    let storage: Box<dyn SyncStorage> = match account.url.strip_prefix(LOCAL_SCHEME) {
+        Some(root) => Box::new(LocalStorage::connect(root).await?),
+        None => Box::new(WebDavStorage::connect(account).await?),
+    };
+    if storage.list_dirs(WEBDAV_SYNC_DIR).await.is_err() {
+        storage.mkdir(WEBDAV_SYNC_DIR).await?;
+    }
+    Ok(storage)
+}
+
+/// 以本地文件夹作为远端存储 用于测试
+#[cfg(test)]
+pub(super) async fn local(root: &std::path::Path) -> Box<dyn SyncStorage> {
+    std::fs::create_dir_all(root).unwrap();
+    let account = AccountInfo {
+        user: String::new(),
+        passwd: String::new(),
+        legacy_passwd: None,
+        url: format!("{}{}", LOCAL_SCHEME, root.display()),
+    };
+    connect(&account).await.unwrap()
+}
@@ -0,0 +1,103 @@
+use crate::common::error::ServiceError;
+use crate::service::syncfile::storage::{ByteStream, SyncStorage};
+use crate::service::syncfile::AccountInfo;
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::StreamExt;
+use reqwest_dav::list_cmd::ListEntity;
+use reqwest_dav::re_exports::reqwest::Body;
+use reqwest_dav::{Auth, Client, ClientBuilder, Depth};
+
+/// webdav存储
+pub(super) struct WebDavStorage {
+    client: Client,
+}
+
+impl WebDavStorage {
+    /// 连接webdav服务
+    pub(super) async fn connect(account: &AccountInfo) -> Result<Self> {
+        // 密码丢失时不以空密码登录
+        if account.passwd.is_empty() {
+            return Err(ServiceError::NotLoggedIn("账户密码为空，请重新填写".to_string()).into());
+        }
+        let client = ClientBuilder::new()
+            .set_host(account.url.to_string())
+            .set_auth(Auth::Basic(
+                account.user.to_owned(),
+                account.passwd.to_owned(),
+            ))
+            .build()?;
+        let _ = client.list("/", Depth::Number(0)).await?;
+        Ok(Self { client })
+    }
+}
+
+#[async_trait]
+impl SyncStorage for WebDavStorage {
+    async fn list_dirs(&self, dir: &str) -> Result<Vec<String>> {
+        let files = self.client.list(dir, Depth::Number(1)).await?;
+        Ok(files
+            .iter()
+            .skip(1)
+            .filter_map(|file| match file {
+                ListEntity::Folder(folder) => folder
+                    .href
+                    .split_once(dir)
+                    .map(|x| x.1.trim_end_matches('/').to_string()),
+                _ => None,
+            })
+            .collect())
+    }
+
+    async fn version(&self, path: &str) -> Result<String> {
+        let files = self.client.list(path, Depth::Number(0)).await?;
+        match files.first() {
+            Some(ListEntity::File(file)) => Ok(file.tag.clone().unwrap_or_else(|| {
+                format!("{}-{}", file.last_modified.timestamp_millis(), file.content_length)
+            })),
+            _ => Err(ServiceError::NotFound(format!("{}不存在或非文件", path)).into()),
+        }
+    }
+
+    async fn read(&self, path: &str) -> Result<Vec<u8>> {
+        let rsp = self.client.get(path).await?;
+        Ok(rsp.bytes().await?.to_vec())
+    }
+
+    async fn write(&self, path: &str, data: Vec<u8>) -> Result<()> {
+        self.client.put(path, Body::from(data)).await?;
+        Ok(())
+    }
+
+    async fn put(&self, path: &str, data: ByteStream) -> Result<()> {
+        self.client.put(path, Body::wrap_stream(data)).await?;
+        Ok(())
+    }
+
+    async fn get(&self, path: &str) -> Result<ByteStream> {
+        let rsp = self.client.get(path).await?;
+        Ok(Box::pin(
+            rsp.bytes_stream().map(|x| x.map_err(std::io::Error::other)),
+        ))
+    }
+
+    async fn delete(&self, path: &str) -> Result<()> {
+        self.client.delete(path).await?;
+        Ok(())
+    }
+
+    async fn mkdir(&self, path: &str) -> Result<()> {
+        self.client.mkcol(path).await?;
+        Ok(())
+    }
+
+    async fn mv(&self, from: &str, to: &str) -> Result<()> {
+        self.client.mv(from, to).await?;
+        Ok(())
+    }
+
+    async fn cp(&self, from: &str, to: &str) -> Result<()> {
+        self.client.cp(from, to).await?;
+        Ok(())
+    }
+}
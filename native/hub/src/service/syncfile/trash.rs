@@ -0,0 +1,178 @@
+use crate::common::global_data::GlobalData;
+use crate::common::utils::{get_cache_dir, sha256};
+use crate::messages::syncfile::{SyncTrashItemMsg, TrashReasonEnumMsg};
+use crate::service::syncfile::diff::local_path;
+use crate::service::syncfile::now_millis;
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tokio::fs::{copy, create_dir_all, metadata, remove_dir_all, remove_file, rename};
+
+const TRASH_CACHE: &str = "syncTrashCache";
+const TRASH_DIR: &str = "sync_trash";
+const DAY_MILLIS: u64 = 24 * 60 * 60 * 1000;
+
+/// 本地回收站
+/// 同步删除或覆盖的本地文件按 远端路径/记录id/文件路径 保存在缓存目录下
+#[derive(Debug, Default)]
+pub(super) struct SyncTrash {
+    // 最新的在前
+    items: Mutex<Vec<SyncTrashItemMsg>>,
+}
+
+impl SyncTrash {
+    /// 读取回收站记录
+    pub(super) async fn load(global_data: &GlobalData) -> Self {
+        let items = global_data
+            .get_data(TRASH_CACHE.to_string())
+            .await
+            .unwrap_or_default();
+        Self {
+            items: Mutex::new(items),
+        }
+    }
+
+    /// 保存回收站记录
+    pub(super) async fn save(&self, global_data: &GlobalData) -> Result<()> {
+        let items = self.items.lock().unwrap().clone();
+        global_data.set_data(TRASH_CACHE.to_string(), &items).await
+    }
+
+    /// 将本地文件移入回收站 文件不存在时忽略
+    pub(super) async fn trash(
+        &self,
+        remote_dir: &str,
+        run_id: u64,
+        file: &str,
+        path: &Path,
+        reason: TrashReasonEnumMsg,
+    ) -> Result<()> {
+        let Ok(meta) = metadata(path).await else {
+            return Ok(());
+        };
+        let mut item = SyncTrashItemMsg {
+            id: String::new(),
+            remote_dir: remote_dir.to_string(),
+            file: file.to_string(),
+            run_id,
+            time: now_millis(),
+            size: if meta.is_dir() { 0 } else { meta.len() },
+            reason,
+        };
+        // 同一次同步中重复移入同一文件时 以序号区分
+        let mut index = 0;
+        let target = loop {
+            item.id = sha256(format!("{remote_dir}{run_id}{file}{index}").as_bytes())[..16].to_string();
+            let exists = self.items.lock().unwrap().iter().any(|x| x.id == item.id);
+            let target = trash_path(&item)?;
+            if !exists && metadata(&target).await.is_err() {
+                break target;
+            }
+            index += 1;
+        };
+        move_path(path, &target).await?;
+        self.items.lock().unwrap().insert(0, item);
+        Ok(())
+    }
+
+    /// 回收站文件列表 remote_dir为空时返回全部
+    pub(super) fn list(&self, remote_dir: &str) -> Vec<SyncTrashItemMsg> {
+        self.items
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|x| remote_dir.is_empty() || x.remote_dir == remote_dir)
+            .cloned()
+            .collect()
+    }
+
+    /// 将文件还原到指定路径
+    pub(super) async fn restore(&self, item: &SyncTrashItemMsg, target: &Path) -> Result<()> {
+        move_path(&trash_path(item)?, target).await?;
+        self.items.lock().unwrap().retain(|x| x.id != item.id);
+        Ok(())
+    }
+
+    /// 永久删除 id为空时清空回收站
+    pub(super) async fn purge(&self, id: &str) -> Result<()> {
+        let items: Vec<SyncTrashItemMsg> = self
+            .items
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|x| id.is_empty() || x.id == id)
+            .cloned()
+            .collect();
+        self.remove(items).await
+    }
+
+    /// 清理超过保留天数的文件
+    pub(super) async fn expire(&self, days: u32) -> Result<()> {
+        if days == 0 {
+            return Ok(());
+        }
+        let deadline = now_millis().saturating_sub(days as u64 * DAY_MILLIS);
+        let items: Vec<SyncTrashItemMsg> = self
+            .items
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|x| x.time < deadline)
+            .cloned()
+            .collect();
+        self.remove(items).await
+    }
+
+    /// 删除文件及记录
+    async fn remove(&self, items: Vec<SyncTrashItemMsg>) -> Result<()> {
+        for item in items {
+            let path = trash_path(&item)?;
+            let result = if item.file.ends_with('/') {
+                remove_dir_all(&path).await
+            } else {
+                remove_file(&path).await
+            };
+            match result {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+            self.items.lock().unwrap().retain(|x| x.id != item.id);
+        }
+        Ok(())
+    }
+}
+
+/// 回收站中的文件路径
+fn trash_path(item: &SyncTrashItemMsg) -> Result<PathBuf> {
+    let mut dir = get_cache_dir()?;
+    dir.push(TRASH_DIR);
+    dir.push(item.remote_dir.trim_matches('/'));
+    dir.push(&item.id);
+    Ok(local_path(&dir.to_string_lossy(), &item.file))
+}
+
+/// 移动文件或文件夹 跨文件系统时复制后删除
+async fn move_path(from: &Path, to: &Path) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        create_dir_all(parent).await?;
+    }
+    if rename(from, to).await.is_ok() {
+        return Ok(());
+    }
+    if metadata(from).await?.is_dir() {
+        for entry in walkdir::WalkDir::new(from) {
+            let entry = entry?;
+            let target = to.join(entry.path().strip_prefix(from)?);
+            if entry.file_type().is_dir() {
+                create_dir_all(&target).await?;
+            } else {
+                copy(entry.path(), &target).await?;
+            }
+        }
+        remove_dir_all(from).await?;
+    } else {
+        copy(from, to).await?;
+        remove_file(from).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,154 @@
+use crate::common::error::ServiceError;
+use crate::common::WEBDAV_SYNC_DIR;
+use crate::messages::syncfile::{RemoteVersionMsg, RestoreRemoteVersionMsg};
+use crate::service::syncfile::crypto::{remote_path, SyncCipher};
+use crate::service::syncfile::storage::SyncStorage;
+use crate::service::syncfile::{now_millis, RemoteFileMedata, SyncFileService};
+use anyhow::Result;
+use log::warn;
+
+/// 远端历史版本文件夹 位于WEBDAV_SYNC_DIR下
+pub(super) const VERSIONS_DIR: &str = ".versions/";
+// 每个文件保留的历史版本数量
+const MAX_VERSIONS: usize = 10;
+
+impl SyncFileService {
+    /// 文件或文件夹移入历史版本前的记录 文件夹时记录其下所有文件
+    pub(super) fn version_records(
+        r_metadata: &RemoteFileMedata,
+        file: &str,
+        time: u64,
+    ) -> Vec<RemoteVersionMsg> {
+        r_metadata
+            .files
+            .iter()
+            .filter(|(k, _)| {
+                !k.ends_with('/') && (k.as_str() == file || (file.ends_with('/') && k.starts_with(file)))
+            })
+            .map(|(k, mtime)| RemoteVersionMsg {
+                file: k.clone(),
+                time,
+                mtime: *mtime as u64,
+                size: r_metadata.sizes.get(k).copied().unwrap_or_default(),
+                hash: r_metadata.hashes.get(k).cloned().unwrap_or_default(),
+            })
+            .collect()
+    }
+
+    /// 将远端文件或文件夹移动到 .versions/时间/远端路径/文件路径
+    pub(super) async fn move_to_version(
+        storage: &dyn SyncStorage,
+        cipher: Option<&SyncCipher>,
+        remote_dir: &str,
+        file: &str,
+        time: u64,
+    ) -> Result<()> {
+        let target = version_path(cipher, remote_dir, file, time);
+        mkdir_parents(storage, &target).await;
+        storage
+            .mv(&remote_path(cipher, remote_dir, file), &target)
+            .await?;
+        Ok(())
+    }
+
+    /// 记录历史版本 超出数量的旧版本从远端删除
+    pub(super) async fn add_remote_versions(
+        storage: &dyn SyncStorage,
+        cipher: Option<&SyncCipher>,
+        r_metadata: &mut RemoteFileMedata,
+        remote_dir: &str,
+        versions: Vec<RemoteVersionMsg>,
+    ) {
+        if versions.is_empty() {
+            return;
+        }
+        for version in versions {
+            r_metadata.versions.insert(0, version);
+        }
+        let mut counts = ahash::AHashMap::new();
+        let mut expired = Vec::new();
+        r_metadata.versions.retain(|x| {
+            let count = counts.entry(x.file.clone()).or_insert(0);
+            *count += 1;
+            if *count > MAX_VERSIONS {
+                expired.push(x.clone());
+                return false;
+            }
+            true
+        });
+        for version in expired {
+            let path = version_path(cipher, remote_dir, &version.file, version.time);
+            if let Err(e) = storage.delete(&path).await {
+                warn!("删除远端历史版本{}失败: {}", path, e);
+            }
+        }
+    }
+
+    /// 将历史版本还原到原路径 当前文件先移入历史版本
+    pub(super) async fn restore_version(
+        storage: &dyn SyncStorage,
+        cipher: Option<&SyncCipher>,
+        r_metadata: &mut RemoteFileMedata,
+        req: &RestoreRemoteVersionMsg,
+    ) -> Result<()> {
+        let version = r_metadata
+            .versions
+            .iter()
+            .find(|x| x.file == req.file && x.time == req.time)
+            .cloned()
+            .ok_or_else(|| ServiceError::NotFound(format!("{}不存在该历史版本", req.file)))?;
+        let remote_file = remote_path(cipher, &req.remote_dir, &req.file);
+        let mut versions = Vec::new();
+        if r_metadata.files.contains_key(&req.file) {
+            let now = now_millis();
+            versions = Self::version_records(r_metadata, &req.file, now);
+            Self::move_to_version(storage, cipher, &req.remote_dir, &req.file, now).await?;
+        } else {
+            mkdir_parents(storage, &remote_file).await;
+        }
+        storage
+            .cp(&version_path(cipher, &req.remote_dir, &req.file, req.time), &remote_file)
+            .await?;
+
+        // 更新远端文件属性 补全上级文件夹
+        let mut end = 0;
+        while let Some(i) = req.file[end..].find('/') {
+            end += i + 1;
+            if end > 1 {
+                r_metadata
+                    .files
+                    .entry(req.file[..end].to_string())
+                    .or_insert(version.mtime as u128);
+            }
+        }
+        r_metadata.files.insert(req.file.clone(), version.mtime as u128);
+        r_metadata.sizes.insert(req.file.clone(), version.size);
+        if version.hash.is_empty() {
+            r_metadata.hashes.remove(&req.file);
+        } else {
+            r_metadata.hashes.insert(req.file.clone(), version.hash);
+        }
+        Self::add_remote_versions(storage, cipher, r_metadata, &req.remote_dir, versions).await;
+        Ok(())
+    }
+}
+
+/// 历史版本路径 加密文件夹的文件名与远端文件一致
+fn version_path(cipher: Option<&SyncCipher>, remote_dir: &str, file: &str, time: u64) -> String {
+    remote_path(cipher, &format!("{VERSIONS_DIR}{time}/{remote_dir}"), file)
+}
+
+/// 创建路径的所有上级文件夹 已存在时忽略错误
+async fn mkdir_parents(storage: &dyn SyncStorage, path: &str) {
+    let Some(path) = path.strip_prefix(WEBDAV_SYNC_DIR) else {
+        return;
+    };
+    let mut dir = WEBDAV_SYNC_DIR.to_string();
+    let mut parts: Vec<&str> = path.split('/').filter(|x| !x.is_empty()).collect();
+    parts.pop();
+    for part in parts {
+        dir.push_str(part);
+        dir.push('/');
+        let _ = storage.mkdir(&dir).await;
+    }
+}
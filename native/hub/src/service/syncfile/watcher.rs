@@ -0,0 +1,328 @@
+use crate::service::syncfile::hash::HashCache;
+use crate::service::syncfile::ignore::{IgnoreRules, IGNORE_FILE};
+use crate::service::syncfile::{sync_path, LocalFileMetadata, SyncFileService, SyncState};
+use ahash::{AHashMap, AHashSet};
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+use tokio::fs::symlink_metadata;
+
+/// 本地文件夹的变更路径
+#[derive(Debug, Default)]
+struct DirtyPaths {
+    // 是否监听中 未监听时每次都全量扫描
+    watched: bool,
+    // 需要全量扫描 首次扫描、监听溢出或文件夹被移动时
+    full: bool,
+    // 变化的路径 绝对路径
+    paths: AHashSet<PathBuf>,
+}
+
+/// 本地文件夹监听
+/// 记录两次扫描之间发生变化的路径，只重新扫描这些路径
+/// 无法监听或重启后退回全量扫描
+pub(super) struct LocalWatcher {
+    // k: 本地文件夹
+    dirty: Arc<Mutex<AHashMap<String, DirtyPaths>>>,
+    // 上次扫描的结果 k: 本地文件夹
+    snapshots: Mutex<AHashMap<String, LocalFileMetadata>>,
+    #[cfg(target_os = "linux")]
+    inotify: Option<linux::Inotify>,
+}
+
+impl LocalWatcher {
+    pub(super) fn new() -> Self {
+        let dirty = Arc::new(Mutex::new(AHashMap::new()));
+        Self {
+            #[cfg(target_os = "linux")]
+            inotify: linux::Inotify::start(dirty.clone())
+                .inspect_err(|e| log::warn!("无法监听本地文件夹变化，将每次全量扫描: {}", e))
+                .ok(),
+            dirty,
+            snapshots: Default::default(),
+        }
+    }
+
+    /// 取出变化的路径 需要全量扫描时为空
+    fn take_dirty(&self, dir: &str) -> Option<Vec<PathBuf>> {
+        // 监听时会锁定监听目录 不能在持有变更路径锁时调用
+        if !self.dirty.lock().unwrap().contains_key(dir) {
+            let watched = self.watch(dir);
+            self.dirty.lock().unwrap().insert(
+                dir.to_string(),
+                DirtyPaths {
+                    watched,
+                    full: true,
+                    paths: AHashSet::new(),
+                },
+            );
+        }
+        let mut dirty = self.dirty.lock().unwrap();
+        let entry = dirty.get_mut(dir)?;
+        let full = std::mem::replace(&mut entry.full, !entry.watched);
+        let paths = std::mem::take(&mut entry.paths);
+        (!full).then(|| paths.into_iter().collect())
+    }
+
+    /// 开始监听 返回是否成功
+    fn watch(&self, dir: &str) -> bool {
+        #[cfg(target_os = "linux")]
+        if let Some(inotify) = &self.inotify {
+            return inotify
+                .watch(dir)
+                .inspect_err(|e| log::warn!("无法监听{}，将每次全量扫描: {}", dir, e))
+                .is_ok();
+        }
+        let _ = dir;
+        false
+    }
+
+    /// 下次扫描时全量扫描
+    pub(super) fn invalidate(&self, dir: &str) {
+        if let Some(entry) = self.dirty.lock().unwrap().get_mut(dir) {
+            entry.full = true;
+        }
+    }
+
+    /// 停止监听文件夹
+    pub(super) fn unwatch(&self, dir: &str) {
+        self.dirty.lock().unwrap().remove(dir);
+        self.snapshots.lock().unwrap().remove(dir);
+        #[cfg(target_os = "linux")]
+        if let Some(inotify) = &self.inotify {
+            inotify.unwatch(dir);
+        }
+    }
+}
+
+impl SyncState {
+    /// 获取本地文件夹的文件属性
+    /// 有上次扫描结果且监听正常时只扫描变化的路径，否则全量扫描
+    pub(super) async fn local_metadata(
+        &self,
+        local_dir: &str,
+        ignore: &IgnoreRules,
+    ) -> Result<LocalFileMetadata> {
+        let dirty = self.watcher.take_dirty(local_dir);
+        let snapshot = self.watcher.snapshots.lock().unwrap().get(local_dir).cloned();
+        let result = match (dirty, snapshot) {
+            // 忽略规则文件变化时 被忽略的路径需要重新判断
+            (Some(paths), Some(mut snapshot))
+                if !paths.iter().any(|x| *x == Path::new(local_dir).join(IGNORE_FILE)) =>
+            {
+                SyncFileService::refresh_local(local_dir, &mut snapshot, paths, &self.hash_cache, ignore)
+                    .await
+                    .map(|_| snapshot)
+            }
+            _ => SyncFileService::get_newest_file(local_dir, &self.hash_cache, ignore).await,
+        };
+        match &result {
+            Ok(metadata) => {
+                self.watcher
+                    .snapshots
+                    .lock()
+                    .unwrap()
+                    .insert(local_dir.to_string(), metadata.clone());
+            }
+            Err(_) => self.watcher.invalidate(local_dir),
+        }
+        result
+    }
+}
+
+impl SyncFileService {
+    /// 重新扫描变化的路径 更新上次扫描的结果
+    async fn refresh_local(
+        dir: &str,
+        metadata: &mut LocalFileMetadata,
+        paths: Vec<PathBuf>,
+        hash_cache: &HashCache,
+        ignore: &IgnoreRules,
+    ) -> Result<()> {
+        let root = Path::new(dir);
+        for path in paths {
+            if path.to_str().is_none() || !path.starts_with(root) || path == root {
+                continue;
+            }
+            // 上级文件夹被忽略时不处理
+            let parent = path.parent().filter(|x| *x != root);
+            if parent.is_some_and(|x| ignore.is_ignored(&sync_path(dir, x, true))) {
+                continue;
+            }
+            // 移除该路径及其下的旧记录
+            let key = sync_path(dir, &path, false);
+            let prefix = format!("{}/", key);
+            let stale = |x: &String| *x == key || x.starts_with(&prefix);
+            metadata.files.retain(|k, _| !stale(k));
+            metadata.hashes.retain(|k, _| !stale(k));
+            metadata.sizes.retain(|k, _| !stale(k));
+            metadata.ignored.retain(|k| !stale(k));
+            if symlink_metadata(&path).await.is_ok() {
+                Self::scan_local(dir, &path, hash_cache, ignore, metadata, &mut AHashSet::new()).await?;
+            }
+            // 更新上级文件夹的修改时间
+            if let Some(parent) = parent {
+                let key = sync_path(dir, parent, true);
+                if metadata.files.contains_key(&key)
+                    && let Ok(x) = symlink_metadata(parent).await
+                {
+                    let time = x.modified()?.duration_since(UNIX_EPOCH)?.as_millis();
+                    metadata.files.insert(key, time);
+                }
+            }
+        }
+        metadata.last_time = metadata.files.values().max().copied().unwrap_or(0);
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use crate::service::syncfile::watcher::DirtyPaths;
+    use ahash::AHashMap;
+    use anyhow::Result;
+    use futures::StreamExt;
+    use inotify::{EventMask, WatchDescriptor, WatchMask, Watches};
+    use log::{error, warn};
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, Mutex};
+    use tokio::task::JoinHandle;
+
+    type DirtyMap = Arc<Mutex<AHashMap<String, DirtyPaths>>>;
+
+    /// 监听的目录 inotify不递归，每个子文件夹单独监听
+    struct Watched {
+        watches: Watches,
+        // k: 监听描述符 v: (同步文件夹, 监听的目录)
+        dirs: AHashMap<WatchDescriptor, (String, PathBuf)>,
+    }
+
+    impl Watched {
+        /// 监听目录及其下所有子文件夹
+        fn add(&mut self, root: &str, dir: &Path) -> Result<()> {
+            let mask = WatchMask::MODIFY
+                | WatchMask::ATTRIB
+                | WatchMask::CLOSE_WRITE
+                | WatchMask::CREATE
+                | WatchMask::DELETE
+                | WatchMask::MOVED_FROM
+                | WatchMask::MOVED_TO
+                | WatchMask::DELETE_SELF
+                | WatchMask::MOVE_SELF;
+            let walker = walkdir::WalkDir::new(dir)
+                .into_iter()
+                .filter_map(|x| x.ok())
+                .filter(|x| x.file_type().is_dir());
+            for entry in walker {
+                let wd = self.watches.add(entry.path(), mask)?;
+                self.dirs
+                    .insert(wd, (root.to_string(), entry.path().to_path_buf()));
+            }
+            Ok(())
+        }
+    }
+
+    /// inotify监听 事件在后台任务中记录到变更路径
+    pub(super) struct Inotify {
+        watched: Arc<Mutex<Watched>>,
+        task: JoinHandle<()>,
+    }
+
+    impl Inotify {
+        pub(super) fn start(dirty: DirtyMap) -> Result<Self> {
+            let inotify = inotify::Inotify::init()?;
+            let watched = Arc::new(Mutex::new(Watched {
+                watches: inotify.watches(),
+                dirs: AHashMap::new(),
+            }));
+            let mut events = inotify.into_event_stream([0; 4096])?;
+            let task = {
+                let watched = watched.clone();
+                tokio::spawn(async move {
+                    while let Some(event) = events.next().await {
+                        let event = match event {
+                            Ok(x) => x,
+                            Err(e) => {
+                                error!("读取本地文件夹变化失败: {}", e);
+                                break;
+                            }
+                        };
+                        let mut watched = watched.lock().unwrap();
+                        let mut dirty = dirty.lock().unwrap();
+                        // 事件队列溢出 无法确定变化的路径
+                        if event.mask.contains(EventMask::Q_OVERFLOW) {
+                            dirty.values_mut().for_each(|x| x.full = true);
+                            continue;
+                        }
+                        if event.mask.contains(EventMask::IGNORED) {
+                            watched.dirs.remove(&event.wd);
+                            continue;
+                        }
+                        let Some((root, dir)) = watched.dirs.get(&event.wd).cloned() else {
+                            continue;
+                        };
+                        let Some(entry) = dirty.get_mut(&root) else {
+                            continue;
+                        };
+                        // 同步文件夹本身被移动或删除
+                        if Path::new(&root) == dir
+                            && event.mask.intersects(EventMask::DELETE_SELF | EventMask::MOVE_SELF)
+                        {
+                            entry.full = true;
+                            continue;
+                        }
+                        let path = match &event.name {
+                            Some(name) => dir.join(name),
+                            None => dir,
+                        };
+                        // 新的子文件夹 加入监听
+                        if event.mask.contains(EventMask::ISDIR)
+                            && event.mask.intersects(EventMask::CREATE | EventMask::MOVED_TO)
+                            && let Err(e) = watched.add(&root, &path)
+                        {
+                            warn!("无法监听{}: {}", path.display(), e);
+                            entry.full = true;
+                        }
+                        entry.paths.insert(path);
+                    }
+                })
+            };
+            Ok(Self { watched, task })
+        }
+
+        /// 监听同步文件夹
+        pub(super) fn watch(&self, dir: &str) -> Result<()> {
+            let mut watched = self.watched.lock().unwrap();
+            let result = watched.add(dir, Path::new(dir));
+            if result.is_err() {
+                Self::remove(&mut watched, dir);
+            }
+            result
+        }
+
+        /// 取消监听同步文件夹
+        pub(super) fn unwatch(&self, dir: &str) {
+            Self::remove(&mut self.watched.lock().unwrap(), dir);
+        }
+
+        fn remove(watched: &mut Watched, dir: &str) {
+            let wds: Vec<WatchDescriptor> = watched
+                .dirs
+                .iter()
+                .filter(|(_, (root, _))| root == dir)
+                .map(|(wd, _)| wd.clone())
+                .collect();
+            for wd in wds {
+                watched.dirs.remove(&wd);
+                let _ = watched.watches.remove(wd);
+            }
+        }
+    }
+
+    impl Drop for Inotify {
+        fn drop(&mut self) {
+            self.task.abort();
+        }
+    }
+}
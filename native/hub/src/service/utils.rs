@@ -1,9 +1,9 @@
 use std::io::Read;
 use crate::messages::common::{BoolMsg, DataMsg, PairStringMsg, StringMsg};
-use crate::service::service::ImmService;
+use crate::service::service::{FuncDescriptor, ImmService, ServiceDescriptor, EMPTY_MSG};
 use std::ffi::OsStr;
 use std::path::PathBuf;
-use std::time::UNIX_EPOCH;
+use std::time::{Duration, UNIX_EPOCH};
 
 use crate::common::utils::{get_cache_dir, sha256};
 use crate::messages::utils::{CompressLocalPicMsg, CompressLocalPicRspMsg, QrCodeDataMsg, QrCodeDataMsgList, SplitImageMsg};
@@ -23,6 +23,25 @@ pub struct UtilsService {
     global_data: GlobalData,
 }
 
+/// 服务描述
+pub const DESCRIPTOR: ServiceDescriptor = ServiceDescriptor {
+    name: "UtilsService",
+    funcs: &[
+        FuncDescriptor::new("compress_local_img", "CompressLocalPicMsg", "CompressLocalPicRspMsg"),
+        FuncDescriptor::new("gen_text_qr_code", "StringMsg", "DataMsg"),
+        FuncDescriptor::new("gen_file_qr_code", "StringMsg", "DataMsg"),
+        FuncDescriptor::new("detect_qr_code", "DataMsg", "QrCodeDataMsgList"),
+        FuncDescriptor::new("detect_file_qr_code", "StringMsg", "QrCodeDataMsgList"),
+        FuncDescriptor::new("split_img", "SplitImageMsg", "DataMsg"),
+        FuncDescriptor::new("get_data", "StringMsg", "StringMsg"),
+        FuncDescriptor::new("network_status", EMPTY_MSG, "BoolMsg"),
+        FuncDescriptor::new("set_data", "PairStringMsg", EMPTY_MSG),
+        FuncDescriptor::new("notify", "StringMsg", EMPTY_MSG),
+    ],
+    platforms: &[],
+    timeout: Some(Duration::from_secs(120)),
+};
+
 #[async_trait::async_trait]
 impl ImmService for UtilsService {
 